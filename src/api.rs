@@ -1,11 +1,45 @@
 use crate::data;
 use chrono::prelude::{DateTime, Utc};
-use reqwest::header::{HeaderValue, IF_MODIFIED_SINCE, USER_AGENT};
+use chrono::Duration;
+use log::{info, warn};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AGE, CONTENT_TYPE, DATE, IF_MODIFIED_SINCE, USER_AGENT,
+};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::iter::IntoIterator;
 
+pub const API_URL: &str = "https://a.4cdn.org";
 const CLIENT_USER_AGENT: &str = concat!("pagenine/", env!("CARGO_PKG_VERSION"));
 
+/// Catalog along with the board that actually served it.
+///
+/// The board differs from the requested one when the API redirected the
+/// request, for example after the board was renamed.
+#[derive(Debug)]
+pub struct FetchedCatalog {
+    pub catalog: Catalog,
+    pub board: String,
+    /// Time on the server when the catalog was served, from the `Date` header
+    /// plus the `Age` of a cached copy.
+    pub date: Option<DateTime<Utc>>,
+}
+
+impl FetchedCatalog {
+    /// Check if the catalog looks like an old cached copy.
+    ///
+    /// The modification times are compared against the time on the server
+    /// so that our own clock being off either way does not matter. Our
+    /// clock is used only if the server did not send its time. A cache
+    /// passes on the `Date` of the original response, so the time the copy
+    /// has spent in the cache is added to it.
+    pub fn is_stale(&self, threshold: Duration) -> bool {
+        self.catalog
+            .is_stale(self.date.unwrap_or_else(Utc::now), threshold)
+    }
+}
+
 /// 4chan API catalog response.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Catalog(Vec<Page>);
@@ -21,15 +55,47 @@ impl IntoIterator for Catalog {
 
 impl Catalog {
     /// Fetch the current catalog from the API.
+    ///
+    /// With `cache_bust`, a unique query string is added to the URL so that
+    /// the CDN cannot answer with a cached copy.
     pub async fn fetch(
+        client: &reqwest::Client,
+        board: &String,
+        cache_bust: bool,
+    ) -> Result<FetchedCatalog, Box<dyn std::error::Error>> {
+        Catalog::fetch_at(client, API_URL, board, cache_bust).await
+    }
+
+    /// Fetch the current catalog from the API at `api_url`.
+    pub async fn fetch_at(
+        client: &reqwest::Client,
+        api_url: &str,
+        board: &String,
+        cache_bust: bool,
+    ) -> Result<FetchedCatalog, Box<dyn std::error::Error>> {
+        Catalog::fetch_from(client, api_url, board, None, cache_bust)
+            .await?
+            .ok_or_else(|| "Catalog has not been modified".into())
+    }
+
+    /// Fetch the catalog from the API unless it has not been modified since
+    /// `if_modified_since`, in which case there is nothing new to return.
+    pub async fn fetch_if_modified(
+        client: &reqwest::Client,
         board: &String,
         if_modified_since: Option<DateTime<Utc>>,
-    ) -> Result<Catalog, Box<dyn std::error::Error>> {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_str(CLIENT_USER_AGENT).unwrap(),
-        );
+    ) -> Result<Option<FetchedCatalog>, Box<dyn std::error::Error>> {
+        Catalog::fetch_from(client, API_URL, board, if_modified_since, false).await
+    }
+
+    async fn fetch_from(
+        client: &reqwest::Client,
+        api_url: &str,
+        board: &String,
+        if_modified_since: Option<DateTime<Utc>>,
+        cache_bust: bool,
+    ) -> Result<Option<FetchedCatalog>, Box<dyn std::error::Error>> {
+        let mut headers = default_headers();
         if let Some(dt) = if_modified_since {
             let dt_str = dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
             if let Ok(header_value) = HeaderValue::from_str(dt_str.as_str()) {
@@ -37,36 +103,266 @@ impl Catalog {
             }
         }
 
-        let client = reqwest::Client::new();
-        let url = format!("https://a.4cdn.org/{}/catalog.json", board);
-        let response = client.get(url).headers(headers).send().await?;
+        let mut url = format!("{}/{}/catalog.json", api_url, board);
+        if cache_bust {
+            url = format!("{}?t={}", url, Utc::now().timestamp_millis());
+        }
+        let response = client.get(&url).headers(headers).send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json"));
+        if !is_json {
+            // Anything other than a redirect or a missing page is more likely
+            // to be a temporary outage than a board that is gone.
+            let redirected = reqwest::Url::parse(&url).ok().as_ref() != Some(response.url());
+            if !redirected && response.status() != StatusCode::NOT_FOUND {
+                response.error_for_status_ref()?;
+                return Err(format!(
+                    "Unexpected response {} from {}",
+                    response.status(),
+                    response.url()
+                )
+                .into());
+            }
+            return Err(format!(
+                "Board /{}/ has moved or does not exist (got {} from {})",
+                board,
+                response.status(),
+                response.url()
+            )
+            .into());
+        }
+
+        let served_board = board_from_url(response.url()).unwrap_or_else(|| board.clone());
+        if &served_board != board {
+            info!("Board /{}/ was redirected to /{}/", board, served_board);
+        }
+        let date = response
+            .headers()
+            .get(DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .map(|date| date.with_timezone(&Utc) + header_age(response.headers()));
         let catalog = response.json::<Catalog>().await?;
-        Ok(catalog)
+        Ok(Some(FetchedCatalog {
+            catalog: catalog.dedupe(),
+            board: served_board,
+            date,
+        }))
+    }
+
+    /// Remove threads that appear more than once in the catalog.
+    ///
+    /// The CDN can serve a catalog caught in the middle of a reshuffle, in
+    /// which case the same thread shows up on two pages. The copy on the
+    /// lowest page is kept since that is the fresher placement.
+    fn dedupe(self) -> Catalog {
+        let mut lowest_pages: HashMap<i32, i32> = HashMap::new();
+        for page in &self.0 {
+            for thread in &page.threads {
+                lowest_pages
+                    .entry(thread.no)
+                    .and_modify(|lowest| *lowest = (*lowest).min(page.page))
+                    .or_insert(page.page);
+            }
+        }
+
+        let mut seen: HashSet<i32> = HashSet::new();
+        let pages = self
+            .0
+            .into_iter()
+            .map(|mut page| {
+                page.threads.retain(|thread| {
+                    let keep =
+                        lowest_pages.get(&thread.no) == Some(&page.page) && seen.insert(thread.no);
+                    if !keep {
+                        warn!(
+                            "Dropped duplicate of thread {} on page {}",
+                            thread.no, page.page
+                        );
+                    }
+                    keep
+                });
+                page
+            })
+            .collect();
+        Catalog(pages)
+    }
+
+    /// Get the most recent modification time of any thread in the catalog.
+    pub fn newest_modification(&self) -> Option<DateTime<Utc>> {
+        self.0
+            .iter()
+            .flat_map(|page| page.threads.iter())
+            .map(|thread| thread.last_modified)
+            .filter(|timestamp| *timestamp > 0)
+            .max()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+    }
+
+    /// Check if the catalog looks like an old cached copy.
+    ///
+    /// On an active board some thread gets bumped every few minutes, so a
+    /// catalog where nothing has been modified within `threshold` is most
+    /// likely stale. Modification times ahead of `now` count as fresh.
+    pub fn is_stale(&self, now: DateTime<Utc>, threshold: Duration) -> bool {
+        match self.newest_modification() {
+            Some(newest) => now - newest > threshold,
+            None => false,
+        }
+    }
+
+    /// Check if the thread with the given number is in the catalog.
+    pub fn contains(&self, no: i32) -> bool {
+        self.0
+            .iter()
+            .any(|page| page.threads.iter().any(|thread| thread.no == no))
     }
 
     /// Find the first thread with the matching title.
     pub fn find(&self, title: &String) -> Option<data::Thread> {
+        self.find_all(title).into_iter().next()
+    }
+
+    /// Find all threads with the matching title in catalog order.
+    pub fn find_all(&self, title: &String) -> Vec<data::Thread> {
+        self.find_where(|_, sub| sub.contains(title))
+    }
+
+    /// Find the thread with the given number.
+    pub fn find_no(&self, no: i32) -> Option<data::Thread> {
+        self.find_where(|thread_no, _| thread_no == no)
+            .into_iter()
+            .next()
+    }
+
+    /// Find all threads with a subject accepted by `predicate` in catalog order.
+    fn find_where(&self, predicate: impl Fn(i32, &str) -> bool) -> Vec<data::Thread> {
+        let mut matches = Vec::new();
+        let slot_count = self.0.iter().map(|page| page.threads.len() as i32).sum();
+        let mut slot = 0;
         for page in self.clone() {
             let page_length = page.threads.len() as i32;
             for (index, thread) in page.threads.into_iter().enumerate() {
+                slot += 1;
                 if let Some(sub) = thread.sub {
-                    if sub.contains(title) {
-                        return Some(data::Thread {
+                    if predicate(thread.no, &sub) {
+                        matches.push(data::Thread {
                             page: page.page,
                             no: thread.no,
                             sub,
                             time: chrono::offset::Utc::now(),
                             position: index as i32 + 1,
                             page_length,
+                            slot,
+                            slot_count,
+                            stale: false,
                         });
                     }
                 }
             }
         }
-        return None;
+        matches
     }
 }
 
+/// What the API knows about a thread that is not in the catalog.
+#[derive(Debug, PartialEq)]
+pub enum ThreadStatus {
+    /// The thread is still open.
+    Open,
+    /// The thread was moved to the archive at the given time.
+    Archived(DateTime<Utc>),
+    /// The thread was deleted, or pruned on a board without an archive.
+    Gone,
+}
+
+impl ThreadStatus {
+    /// Look the thread up from the API at `api_url`.
+    pub async fn fetch_from(
+        client: &reqwest::Client,
+        api_url: &str,
+        board: &String,
+        no: i32,
+    ) -> Result<ThreadStatus, Box<dyn std::error::Error>> {
+        let response = client
+            .get(format!("{}/{}/thread/{}.json", api_url, board, no))
+            .headers(default_headers())
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(ThreadStatus::Gone);
+        }
+        let posts = response.error_for_status()?.json::<Posts>().await?;
+        let op = posts.posts.first().ok_or("Thread has no posts")?;
+        match op.archived {
+            0 => Ok(ThreadStatus::Open),
+            _ => Ok(ThreadStatus::Archived(
+                DateTime::from_timestamp(op.archived_on, 0).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// 4chan API thread response.
+#[derive(Default, Debug, Deserialize)]
+struct Posts {
+    posts: Vec<Post>,
+}
+
+#[derive(Default, Debug, Deserialize)]
+struct Post {
+    #[serde(default)]
+    archived: i32,
+    #[serde(default)]
+    archived_on: i64,
+}
+
+/// 4chan API board list response.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Boards {
+    pub boards: Vec<Board>,
+}
+
+impl Boards {
+    /// Fetch the list of boards from the API.
+    pub async fn fetch(client: &reqwest::Client) -> Result<Boards, Box<dyn std::error::Error>> {
+        Boards::fetch_from(client, API_URL).await
+    }
+
+    async fn fetch_from(
+        client: &reqwest::Client,
+        api_url: &str,
+    ) -> Result<Boards, Box<dyn std::error::Error>> {
+        let url = format!("{}/boards.json", api_url);
+        let response = client
+            .get(url)
+            .headers(default_headers())
+            .send()
+            .await?
+            .error_for_status()?;
+        let boards = response.json::<Boards>().await?;
+        Ok(boards)
+    }
+
+    /// Find the board with the given name.
+    pub fn find(&self, board: &String) -> Option<&Board> {
+        self.boards.iter().find(|item| &item.board == board)
+    }
+}
+
+/// Partial schema for each board.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    pub board: String,
+    pub title: String,
+}
+
 /// Top-level object in the catalog response.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Page {
@@ -79,4 +375,507 @@ pub struct Page {
 pub struct Thread {
     pub no: i32,
     pub sub: Option<String>,
+    #[serde(default)]
+    pub last_modified: i64,
+}
+
+/// Get the board name from a catalog URL such as `/vg/catalog.json`.
+fn board_from_url(url: &reqwest::Url) -> Option<String> {
+    let mut segments = url.path_segments()?.rev();
+    match (segments.next(), segments.next()) {
+        (Some("catalog.json"), Some(board)) if !board.is_empty() => Some(board.to_string()),
+        _ => None,
+    }
+}
+
+/// Get the time a cache has held the response from the `Age` header.
+fn header_age(headers: &HeaderMap) -> Duration {
+    headers
+        .get(AGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .map_or(Duration::zero(), Duration::seconds)
+}
+
+fn default_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(CLIENT_USER_AGENT).unwrap(),
+    );
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::client;
+    use test_case::test_case;
+
+    const DUPLICATE_CATALOG: &str = r#"[
+        {"page": 1, "threads": [
+            {"no": 100, "sub": "Sticky"},
+            {"no": 101}
+        ]},
+        {"page": 2, "threads": [
+            {"no": 200, "sub": "/xyz/ - Xyz General #213"},
+            {"no": 201, "sub": "Something else"}
+        ]},
+        {"page": 3, "threads": [
+            {"no": 300},
+            {"no": 200, "sub": "/xyz/ - Xyz General #213"},
+            {"no": 301}
+        ]}
+    ]"#;
+
+    fn parse(json: &str) -> Catalog {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn make_catalog(last_modified: &[i64]) -> Catalog {
+        let threads = last_modified
+            .iter()
+            .enumerate()
+            .map(|(index, last_modified)| Thread {
+                no: index as i32 + 1,
+                sub: None,
+                last_modified: *last_modified,
+            })
+            .collect();
+        Catalog(vec![Page { page: 1, threads }])
+    }
+
+    #[test]
+    fn catalog_dedupe_keeps_lowest_page() {
+        let catalog = parse(DUPLICATE_CATALOG).dedupe();
+        let occurrences: Vec<i32> = catalog
+            .clone()
+            .into_iter()
+            .flat_map(|page| {
+                let page_number = page.page;
+                page.threads
+                    .into_iter()
+                    .filter(|thread| thread.no == 200)
+                    .map(move |_| page_number)
+            })
+            .collect();
+        assert_eq!(occurrences, vec![2]);
+
+        let pages: Vec<usize> = catalog.into_iter().map(|page| page.threads.len()).collect();
+        assert_eq!(pages, vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn catalog_dedupe_lower_page_listed_last() {
+        let mut catalog = parse(DUPLICATE_CATALOG);
+        catalog.0.reverse();
+        let thread = catalog.dedupe().find(&String::from("Xyz General")).unwrap();
+        assert_eq!(thread.no, 200);
+        assert_eq!(thread.page, 2);
+        assert_eq!(thread.position, 1);
+        assert_eq!(thread.page_length, 2);
+    }
+
+    #[test]
+    fn catalog_dedupe_stable_selection() {
+        let title = String::from("Xyz General");
+        let first = parse(DUPLICATE_CATALOG).dedupe().find(&title).unwrap();
+        let second = parse(DUPLICATE_CATALOG).dedupe().find(&title).unwrap();
+        assert_eq!(
+            (first.page, first.position, first.page_length),
+            (second.page, second.position, second.page_length)
+        );
+        assert_eq!((first.page, first.position), (2, 1));
+    }
+
+    #[test]
+    fn catalog_dedupe_same_page() {
+        let catalog = parse(r#"[{"page": 1, "threads": [{"no": 1}, {"no": 2}, {"no": 1}]}]"#);
+        let threads: Vec<i32> = catalog
+            .dedupe()
+            .into_iter()
+            .flat_map(|page| page.threads)
+            .map(|thread| thread.no)
+            .collect();
+        assert_eq!(threads, vec![1, 2]);
+    }
+
+    #[test]
+    fn catalog_contains() {
+        let catalog =
+            parse(r#"[{"page": 1, "threads": [{"no": 1}]}, {"page": 2, "threads": [{"no": 3}]}]"#);
+        assert!(catalog.contains(1));
+        assert!(catalog.contains(3));
+        assert!(!catalog.contains(2));
+    }
+
+    #[test]
+    fn catalog_find_no() {
+        let catalog = parse(
+            r#"[
+                {"page": 1, "threads": [{"no": 1, "sub": "/xyz/ #1"}, {"no": 2}]},
+                {"page": 2, "threads": [{"no": 3, "sub": "Other"}]}
+            ]"#,
+        );
+        let thread = catalog.find_no(3).unwrap();
+        assert_eq!(
+            (thread.page, thread.position, thread.sub.as_str()),
+            (2, 1, "Other")
+        );
+        assert!(catalog.find_no(4).is_none());
+    }
+
+    #[test_case(r#"{"posts": [{"no": 1}]}"#, ThreadStatus::Open; "open")]
+    #[test_case(
+        r#"{"posts": [{"no": 1, "archived": 1, "archived_on": 1700000000}]}"#,
+        ThreadStatus::Archived(DateTime::from_timestamp(1700000000, 0).unwrap());
+        "archived"
+    )]
+    #[tokio::test]
+    async fn thread_status_fetch(body: &str, status: ThreadStatus) {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/thread/1.json")
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create_async()
+            .await;
+        let result = ThreadStatus::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &String::from("vg"),
+            1,
+        )
+        .await;
+        assert_eq!(result.unwrap(), status);
+    }
+
+    #[tokio::test]
+    async fn thread_status_fetch_gone() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/thread/1.json")
+            .with_status(404)
+            .create_async()
+            .await;
+        let result = ThreadStatus::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &String::from("vg"),
+            1,
+        )
+        .await;
+        assert_eq!(result.unwrap(), ThreadStatus::Gone);
+    }
+
+    #[test]
+    fn catalog_find_all() {
+        let catalog = parse(
+            r#"[
+                {"page": 1, "threads": [{"no": 1, "sub": "/xyz/ #1"}, {"no": 2}]},
+                {"page": 2, "threads": [{"no": 3, "sub": "Other"}, {"no": 4, "sub": "/xyz/ #2"}]}
+            ]"#,
+        );
+        let matches: Vec<(i32, i32, i32)> = catalog
+            .find_all(&String::from("/xyz/"))
+            .into_iter()
+            .map(|thread| (thread.no, thread.page, thread.position))
+            .collect();
+        assert_eq!(matches, vec![(1, 1, 1), (4, 2, 2)]);
+        let slots: Vec<(i32, i32)> = catalog
+            .find_all(&String::from("/xyz/"))
+            .into_iter()
+            .map(|thread| (thread.slot, thread.slot_count))
+            .collect();
+        assert_eq!(slots, vec![(1, 4), (4, 4)]);
+        assert!(catalog.find_all(&String::from("/abc/")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn catalog_fetch_board() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 1, "sub": "/xyz/"}]}]"#)
+            .create_async()
+            .await;
+
+        let board = String::from("vg");
+        let fetched = Catalog::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &board,
+            None,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(fetched.board, "vg");
+        assert!(fetched.catalog.find(&String::from("/xyz/")).is_some());
+    }
+
+    #[tokio::test]
+    async fn catalog_fetch_redirect_to_new_board() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/old/catalog.json")
+            .with_status(301)
+            .with_header("location", &format!("{}/new/catalog.json", server.url()))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/new/catalog.json")
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 1, "sub": "/xyz/"}]}]"#)
+            .create_async()
+            .await;
+
+        let board = String::from("old");
+        let fetched = Catalog::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &board,
+            None,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(fetched.board, "new");
+        assert!(fetched.catalog.find(&String::from("/xyz/")).is_some());
+    }
+
+    #[tokio::test]
+    async fn catalog_fetch_redirect_to_error_page() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/old/catalog.json")
+            .with_status(302)
+            .with_header("location", "/404.html")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/404.html")
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>404 Not Found</body></html>")
+            .create_async()
+            .await;
+
+        let board = String::from("old");
+        let error = Catalog::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &board,
+            None,
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Board /old/ has moved or does not exist"));
+    }
+
+    #[test_case(404, true; "not found")]
+    #[test_case(503, false; "service unavailable")]
+    #[test_case(200, false; "html page")]
+    #[tokio::test]
+    async fn catalog_fetch_error_page(status: usize, moved: bool) {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_status(status)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body>Error</body></html>")
+            .create_async()
+            .await;
+
+        let board = String::from("vg");
+        let error = Catalog::fetch_from(
+            &client::build(None).unwrap(),
+            &server.url(),
+            &board,
+            None,
+            false,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            error.starts_with("Board /vg/ has moved or does not exist"),
+            moved
+        );
+        assert!(error.contains(&status.to_string()));
+    }
+
+    #[tokio::test]
+    async fn catalog_fetch_not_modified() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_status(304)
+            .create_async()
+            .await;
+
+        let board = String::from("vg");
+        let fetched = Catalog::fetch_from(
+            &reqwest::Client::new(),
+            &server.url(),
+            &board,
+            Some(Utc::now()),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(fetched.is_none());
+    }
+
+    #[test_case("https://a.4cdn.org/vg/catalog.json", Some("vg"); "catalog")]
+    #[test_case("https://a.4cdn.org/vg/catalog.json?t=1", Some("vg"); "query string")]
+    #[test_case("https://a.4cdn.org/404.html", None; "error page")]
+    #[test_case("https://a.4cdn.org/catalog.json", None; "missing board")]
+    fn api_board_from_url(url: &str, board: Option<&str>) {
+        let url = reqwest::Url::parse(url).unwrap();
+        assert_eq!(board_from_url(&url), board.map(String::from));
+    }
+
+    #[tokio::test]
+    async fn boards_fetch() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/boards.json")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"boards": [{"board": "vg", "title": "Video Game Generals", "pages": 10}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let boards = Boards::fetch_from(&reqwest::Client::new(), &server.url())
+            .await
+            .unwrap();
+        assert_eq!(
+            boards
+                .find(&String::from("vg"))
+                .map(|board| board.title.as_str()),
+            Some("Video Game Generals")
+        );
+        assert!(boards.find(&String::from("xyz")).is_none());
+    }
+
+    #[test]
+    fn catalog_newest_modification() {
+        let catalog = make_catalog(&[1700000000, 1700000600, 1700000300]);
+        assert_eq!(
+            catalog.newest_modification(),
+            DateTime::from_timestamp(1700000600, 0)
+        );
+        assert_eq!(Catalog::default().newest_modification(), None);
+    }
+
+    #[test_case(60, false; "recently modified")]
+    #[test_case(299, false; "just under threshold")]
+    #[test_case(301, true; "just over threshold")]
+    #[test_case(3600, true; "hour old")]
+    #[test_case(-120, false; "clock behind server")]
+    fn catalog_is_stale(age: i64, stale: bool) {
+        let now = DateTime::from_timestamp(1700000000, 0).unwrap();
+        let catalog = make_catalog(&[now.timestamp() - age - 600, now.timestamp() - age]);
+        assert_eq!(catalog.is_stale(now, Duration::minutes(5)), stale);
+    }
+
+    #[test]
+    fn catalog_is_stale_without_timestamps() {
+        let now = DateTime::from_timestamp(1700000000, 0).unwrap();
+        assert!(!Catalog::default().is_stale(now, Duration::minutes(5)));
+        let catalog = parse(DUPLICATE_CATALOG);
+        assert!(!catalog.is_stale(now, Duration::minutes(5)));
+    }
+
+    #[test_case(None, false; "server time")]
+    #[test_case(Some("60"), false; "briefly cached")]
+    #[test_case(Some("1200"), true; "cached copy")]
+    #[test_case(Some("invalid"), false; "invalid age")]
+    #[tokio::test]
+    async fn catalog_fetch_is_stale(age: Option<&str>, stale: bool) {
+        let mut server = mockito::Server::new_async().await;
+        let mut mock = server
+            .mock("GET", "/vg/catalog.json")
+            .with_header("content-type", "application/json")
+            .with_header("date", "Tue, 14 Nov 2023 22:15:00 GMT")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 1, "last_modified": 1700000000}]}]"#);
+        if let Some(age) = age {
+            mock = mock.with_header("age", age);
+        }
+        mock.create_async().await;
+
+        let fetched = Catalog::fetch_from(
+            &reqwest::Client::new(),
+            &server.url(),
+            &String::from("vg"),
+            None,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(fetched.is_stale(Duration::minutes(5)), stale);
+    }
+
+    #[test_case(60, false; "recently modified")]
+    #[test_case(1200, true; "old copy")]
+    fn fetched_catalog_is_stale_without_date(age: i64, stale: bool) {
+        let fetched = FetchedCatalog {
+            catalog: make_catalog(&[Utc::now().timestamp() - age]),
+            board: String::from("vg"),
+            date: None,
+        };
+        assert_eq!(fetched.is_stale(Duration::minutes(5)), stale);
+    }
+
+    #[tokio::test]
+    async fn catalog_fetch_cache_bust() {
+        let mut server = mockito::Server::new_async().await;
+        let fresh = server
+            .mock("GET", "/vg/catalog.json")
+            .match_query(mockito::Matcher::Regex(String::from("^t=[0-9]+$")))
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 2, "last_modified": 200}]}]"#)
+            .create_async()
+            .await;
+        let cached = server
+            .mock("GET", "/vg/catalog.json")
+            .match_query(mockito::Matcher::Missing)
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 1, "last_modified": 100}]}]"#)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let board = String::from("vg");
+        let catalog = Catalog::fetch_from(&client, &server.url(), &board, None, false)
+            .await
+            .unwrap()
+            .unwrap()
+            .catalog;
+        assert_eq!(
+            catalog.newest_modification(),
+            DateTime::from_timestamp(100, 0)
+        );
+        let catalog = Catalog::fetch_from(&client, &server.url(), &board, None, true)
+            .await
+            .unwrap()
+            .unwrap()
+            .catalog;
+        assert_eq!(
+            catalog.newest_modification(),
+            DateTime::from_timestamp(200, 0)
+        );
+
+        fresh.assert_async().await;
+        cached.assert_async().await;
+    }
 }
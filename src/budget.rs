@@ -0,0 +1,237 @@
+use chrono::prelude::{DateTime, Utc};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+const WINDOW_MINUTES: i64 = 60;
+
+/// Limit on how many notifications are delivered per hour.
+///
+/// Notifications over the limit are held back and summarized in a single
+/// digest once the hour is over.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Budget {
+    #[serde(skip)]
+    limit: u32,
+    window_start: Option<DateTime<Utc>>,
+    delivered: u32,
+    held: Vec<String>,
+}
+
+/// Summary of the notifications held back during a window.
+#[derive(Debug, PartialEq)]
+pub struct Digest {
+    pub message: String,
+    /// Number of held notifications in the summary.
+    count: usize,
+}
+
+impl Budget {
+    pub fn new(limit: u32) -> Budget {
+        Budget {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Take over the window and held notifications of a saved budget while
+    /// keeping the current limit.
+    pub fn restore(&mut self, saved: Budget) {
+        *self = Budget {
+            limit: self.limit,
+            ..saved
+        };
+    }
+
+    /// Check if a notification can be delivered now.
+    ///
+    /// If not, `line` is held back for the digest. Once the window is over,
+    /// notifications are held until the digest has been taken so that it
+    /// goes out first.
+    pub fn admit(&mut self, now: DateTime<Utc>, line: String) -> bool {
+        if self.is_over(now) && self.held.is_empty() {
+            self.window_start = None;
+            self.delivered = 0;
+        }
+        let window_start = *self.window_start.get_or_insert(now);
+        if now - window_start < Duration::minutes(WINDOW_MINUTES) && self.delivered < self.limit {
+            self.delivered += 1;
+            return true;
+        }
+        self.held.push(line);
+        false
+    }
+
+    /// Give back the slot of a notification that could not be delivered.
+    pub fn refund(&mut self) {
+        self.delivered = self.delivered.saturating_sub(1);
+    }
+
+    /// Get the digest of held notifications once the window is over.
+    ///
+    /// The notifications stay held until the digest has been sent.
+    pub fn digest(&self, now: DateTime<Utc>) -> Option<Digest> {
+        if !self.is_over(now) || self.held.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = self.held.iter().map(|line| format!("- {}", line)).collect();
+        Some(Digest {
+            message: format!(
+                "{} held back in the last hour:\n{}",
+                match self.held.len() {
+                    1 => String::from("1 notification"),
+                    count => format!("{} notifications", count),
+                },
+                lines.join("\n")
+            ),
+            count: self.held.len(),
+        })
+    }
+
+    /// Forget the notifications in the sent digest and start the next
+    /// window with the digest as its first notification.
+    pub fn digest_sent(&mut self, now: DateTime<Utc>, digest: &Digest) {
+        self.held.drain(..digest.count.min(self.held.len()));
+        self.window_start = Some(now);
+        self.delivered = 1;
+    }
+
+    fn is_over(&self, now: DateTime<Utc>) -> bool {
+        self.window_start
+            .is_some_and(|start| now - start >= Duration::minutes(WINDOW_MINUTES))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1700000000, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    /// Get the digest and mark it as sent.
+    fn take_digest(budget: &mut Budget, now: DateTime<Utc>) -> Option<String> {
+        let digest = budget.digest(now)?;
+        budget.digest_sent(now, &digest);
+        Some(digest.message)
+    }
+
+    #[test]
+    fn budget_exhausted() {
+        let mut budget = Budget::new(2);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert!(budget.admit(at(10), String::from("second")));
+        assert!(!budget.admit(at(20), String::from("third")));
+        assert!(!budget.admit(at(59), String::from("fourth")));
+        assert_eq!(take_digest(&mut budget, at(59)), None);
+        assert_eq!(
+            take_digest(&mut budget, at(60)),
+            Some(String::from(
+                "2 notifications held back in the last hour:\n- third\n- fourth"
+            ))
+        );
+        assert_eq!(take_digest(&mut budget, at(61)), None);
+
+        // The digest used the first slot of the new window.
+        assert!(budget.admit(at(70), String::from("fifth")));
+        assert!(!budget.admit(at(80), String::from("sixth")));
+    }
+
+    #[test]
+    fn budget_new_window() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert_eq!(take_digest(&mut budget, at(90)), None);
+        assert!(budget.admit(at(90), String::from("second")));
+        assert!(!budget.admit(at(100), String::from("third")));
+    }
+
+    #[test]
+    fn budget_held_until_digest() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert!(!budget.admit(at(10), String::from("second")));
+        assert!(!budget.admit(at(65), String::from("third")));
+        assert_eq!(
+            take_digest(&mut budget, at(66)),
+            Some(String::from(
+                "2 notifications held back in the last hour:\n- second\n- third"
+            ))
+        );
+    }
+
+    #[test]
+    fn budget_single_held() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert!(!budget.admit(at(1), String::from("second")));
+        assert_eq!(
+            take_digest(&mut budget, at(60)),
+            Some(String::from(
+                "1 notification held back in the last hour:\n- second"
+            ))
+        );
+    }
+
+    #[test]
+    fn budget_digest_not_sent() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert!(!budget.admit(at(1), String::from("second")));
+        let digest = budget.digest(at(60)).unwrap();
+        assert_eq!(
+            digest.message,
+            "1 notification held back in the last hour:\n- second"
+        );
+
+        // Until the digest is sent it goes out first.
+        assert!(!budget.admit(at(61), String::from("third")));
+        assert_eq!(
+            take_digest(&mut budget, at(62)),
+            Some(String::from(
+                "2 notifications held back in the last hour:\n- second\n- third"
+            ))
+        );
+    }
+
+    #[test]
+    fn budget_held_while_sending_digest() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("first")));
+        assert!(!budget.admit(at(1), String::from("second")));
+        let digest = budget.digest(at(60)).unwrap();
+        assert!(!budget.admit(at(60), String::from("third")));
+        budget.digest_sent(at(60), &digest);
+        assert_eq!(budget.held, vec![String::from("third")]);
+    }
+
+    #[test]
+    fn budget_refund() {
+        let mut budget = Budget::new(1);
+        assert!(budget.admit(at(0), String::from("failed")));
+        budget.refund();
+        assert!(budget.admit(at(1), String::from("retried")));
+    }
+
+    #[test]
+    fn budget_restore() {
+        let mut saved = Budget::new(1);
+        assert!(saved.admit(at(0), String::from("first")));
+        assert!(!saved.admit(at(1), String::from("second")));
+        let saved: Budget = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+
+        let mut budget = Budget::new(3);
+        budget.restore(saved);
+        assert_eq!(budget.limit, 3);
+        assert!(budget.admit(at(2), String::from("third")));
+        assert!(budget.admit(at(3), String::from("fourth")));
+        assert!(!budget.admit(at(4), String::from("fifth")));
+        assert_eq!(
+            take_digest(&mut budget, at(60)),
+            Some(String::from(
+                "2 notifications held back in the last hour:\n- second\n- fifth"
+            ))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+compile_error!("features `native-tls` and `rustls` are mutually exclusive");
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("either feature `native-tls` or `rustls` must be enabled");
+
+const PROBE_HOST: &str = "a.4cdn.org:443";
+const MAX_REDIRECTS: usize = 5;
+
+/// IP address family that all outgoing traffic is restricted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    fn unspecified_address(&self) -> IpAddr {
+        match self {
+            IpFamily::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpFamily::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    fn matches(&self, address: &SocketAddr) -> bool {
+        match self {
+            IpFamily::V4 => address.is_ipv4(),
+            IpFamily::V6 => address.is_ipv6(),
+        }
+    }
+}
+
+impl std::fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpFamily::V4 => write!(f, "IPv4"),
+            IpFamily::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// Build the HTTP client shared by the API and the notifiers.
+///
+/// Redirects are followed so that moved boards keep working, but only a
+/// few times to avoid long chains. Binding the sockets to the unspecified
+/// address of a family makes the connector skip resolved addresses of the
+/// other family entirely.
+pub fn build(ip_family: Option<IpFamily>) -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder =
+        reqwest::Client::builder().redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS));
+    #[cfg(feature = "native-tls")]
+    {
+        builder = builder.use_native_tls();
+    }
+    #[cfg(feature = "rustls")]
+    {
+        builder = builder.use_rustls_tls();
+    }
+    if let Some(ip_family) = ip_family {
+        builder = builder.local_address(ip_family.unspecified_address());
+    }
+    builder.build()
+}
+
+/// Check that the API host can be routed to using the given family.
+///
+/// Connecting a UDP socket sends nothing but still fails when the kernel
+/// has no route to the address.
+pub async fn check_route(ip_family: IpFamily) -> Result<(), String> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host(PROBE_HOST)
+        .await
+        .map_err(|error| format!("Could not resolve {}: {}", PROBE_HOST, error))?
+        .filter(|address| ip_family.matches(address))
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} has no {} address", PROBE_HOST, ip_family));
+    }
+    let socket = UdpSocket::bind(SocketAddr::new(ip_family.unspecified_address(), 0))
+        .map_err(|error| format!("Could not open an {} socket: {}", ip_family, error))?;
+    match socket.connect(&addresses[..]) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(format!(
+            "No {} route to {}: {}",
+            ip_family, PROBE_HOST, error
+        )),
+    }
+}
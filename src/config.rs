@@ -0,0 +1,219 @@
+use crate::notifier::NotifierKind;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings read from a configuration file.
+///
+/// Every setting is optional and the command line takes precedence.
+#[derive(Default, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub board: Option<String>,
+    pub title: Option<String>,
+    pub label: Option<String>,
+    pub pushover_application_api_token: Option<String>,
+    pub pushover_user_key: Option<String>,
+    pub notifier: Option<NotifierKind>,
+    pub state_file: Option<PathBuf>,
+    #[cfg(unix)]
+    pub log_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Read the configuration from a TOML file.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        toml::from_str(&content)
+            .map_err(|error| format!("Could not parse {}: {}", path.display(), error))
+    }
+
+    /// Load the explicitly given configuration file, or the first default
+    /// configuration file that exists.
+    ///
+    /// A missing explicit file is an error, but it is fine for none of the
+    /// default locations to have a file.
+    pub fn discover(explicit: Option<&Path>) -> Result<Option<(PathBuf, Config)>, String> {
+        Config::discover_in(explicit, default_paths())
+    }
+
+    fn discover_in(
+        explicit: Option<&Path>,
+        default_paths: Vec<PathBuf>,
+    ) -> Result<Option<(PathBuf, Config)>, String> {
+        let path = match explicit {
+            Some(path) => path.to_path_buf(),
+            None => match default_paths.into_iter().find(|path| path.is_file()) {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        let config = Config::load(&path)?;
+        Ok(Some((path, config)))
+    }
+}
+
+/// Get the default configuration file locations in the order they are tried.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn default_paths() -> Vec<PathBuf> {
+    xdg_paths(
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+        std::env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+/// Get the default configuration file locations in the order they are tried.
+#[cfg(any(target_os = "macos", windows))]
+pub fn default_paths() -> Vec<PathBuf> {
+    directories::ProjectDirs::from("", "", "pagenine")
+        .map(|dirs| vec![dirs.config_dir().join(CONFIG_FILE_NAME)])
+        .unwrap_or_default()
+}
+
+/// Build the XDG configuration file locations.
+///
+/// Relative values of `XDG_CONFIG_HOME` are ignored as required by the
+/// base directory specification.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn xdg_paths(xdg_config_home: Option<PathBuf>, home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut config_dirs = Vec::new();
+    if let Some(xdg_config_home) = xdg_config_home.filter(|path| path.is_absolute()) {
+        config_dirs.push(xdg_config_home);
+    }
+    if let Some(home) = home {
+        config_dirs.push(home.join(".config"));
+    }
+    config_dirs.dedup();
+    config_dirs
+        .into_iter()
+        .map(|dir| dir.join("pagenine").join(CONFIG_FILE_NAME))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn write_config(dir: &Path, content: &str) -> PathBuf {
+        let path = dir.join("pagenine").join(CONFIG_FILE_NAME);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn config_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            dir.path(),
+            "board = \"vg\"\ntitle = \"/xyz/\"\nlabel = \"XYZ\"\npushover_user_key = \"user\"\nnotifier = \"stdout\"\n",
+        );
+        assert_eq!(
+            Config::load(&path),
+            Ok(Config {
+                board: Some(String::from("vg")),
+                title: Some(String::from("/xyz/")),
+                label: Some(String::from("XYZ")),
+                pushover_application_api_token: None,
+                pushover_user_key: Some(String::from("user")),
+                notifier: Some(NotifierKind::Stdout),
+                state_file: None,
+                #[cfg(unix)]
+                log_file: None,
+            })
+        );
+    }
+
+    #[test]
+    fn config_load_unknown_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(dir.path(), "bored = \"vg\"\n");
+        assert!(Config::load(&path).is_err());
+    }
+
+    #[test]
+    fn config_discover_explicit_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.toml");
+        assert!(Config::discover(Some(&path)).is_err());
+    }
+
+    #[test]
+    fn config_discover_explicit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(dir.path(), "board = \"vg\"\n");
+        let (found, config) = Config::discover(Some(&path)).unwrap().unwrap();
+        assert_eq!(found, path);
+        assert_eq!(config.board, Some(String::from("vg")));
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn config_xdg_paths() {
+        assert_eq!(
+            xdg_paths(
+                Some(PathBuf::from("/xdg")),
+                Some(PathBuf::from("/home/anon"))
+            ),
+            vec![
+                PathBuf::from("/xdg/pagenine/config.toml"),
+                PathBuf::from("/home/anon/.config/pagenine/config.toml"),
+            ]
+        );
+        assert_eq!(
+            xdg_paths(None, Some(PathBuf::from("/home/anon"))),
+            vec![PathBuf::from("/home/anon/.config/pagenine/config.toml")]
+        );
+        assert_eq!(
+            xdg_paths(
+                Some(PathBuf::from("relative")),
+                Some(PathBuf::from("/home/anon"))
+            ),
+            vec![PathBuf::from("/home/anon/.config/pagenine/config.toml")]
+        );
+        assert_eq!(
+            xdg_paths(
+                Some(PathBuf::from("/home/anon/.config")),
+                Some(PathBuf::from("/home/anon"))
+            ),
+            vec![PathBuf::from("/home/anon/.config/pagenine/config.toml")]
+        );
+        assert!(xdg_paths(None, None).is_empty());
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn config_discover_default() {
+        let xdg = tempfile::tempdir().unwrap();
+        let home = tempfile::tempdir().unwrap();
+        let paths = |xdg_config_home: Option<&Path>| {
+            xdg_paths(
+                xdg_config_home.map(Path::to_path_buf),
+                Some(home.path().to_path_buf()),
+            )
+        };
+
+        assert_eq!(Config::discover_in(None, paths(Some(xdg.path()))), Ok(None));
+
+        let home_path = write_config(&home.path().join(".config"), "board = \"home\"\n");
+        let (found, config) = Config::discover_in(None, paths(Some(xdg.path())))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found, home_path);
+        assert_eq!(config.board, Some(String::from("home")));
+
+        let xdg_path = write_config(xdg.path(), "board = \"xdg\"\n");
+        let (found, config) = Config::discover_in(None, paths(Some(xdg.path())))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found, xdg_path);
+        assert_eq!(config.board, Some(String::from("xdg")));
+
+        let (found, _) = Config::discover_in(None, paths(None)).unwrap().unwrap();
+        assert_eq!(found, home_path);
+    }
+}
@@ -0,0 +1,265 @@
+use log::warn;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// File containing the pid of the running process, removed when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Create the pid file and write the pid of the current process to it.
+    ///
+    /// The file is created exclusively so that of two processes starting at
+    /// the same time only one gets it. A pid file left behind by a process
+    /// that has since died is replaced.
+    pub fn create(path: &Path) -> Result<PidFile, String> {
+        let file = match PidFile::create_new(path) {
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                PidFile::check(path)?;
+                warn!("Replacing stale pid file {}", path.display());
+                match std::fs::remove_file(path) {
+                    Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(format!("Could not remove {}: {}", path.display(), error))
+                    }
+                    _ => PidFile::create_new(path),
+                }
+            }
+            result => result,
+        };
+        let mut file = match file {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(format!("Already running according to {}", path.display()))
+            }
+            Err(error) => return Err(format!("Could not create {}: {}", path.display(), error)),
+        };
+        let pid_file = PidFile {
+            path: path.to_path_buf(),
+        };
+        writeln!(file, "{}", std::process::id())
+            .map_err(|error| format!("Could not write {}: {}", path.display(), error))?;
+        Ok(pid_file)
+    }
+
+    /// Write the pid of the current process to the file again, for example
+    /// after forking into the background.
+    pub fn update(&self) -> Result<(), String> {
+        std::fs::write(&self.path, format!("{}\n", std::process::id()))
+            .map_err(|error| format!("Could not write {}: {}", self.path.display(), error))
+    }
+
+    fn create_new(path: &Path) -> std::io::Result<File> {
+        OpenOptions::new().write(true).create_new(true).open(path)
+    }
+
+    /// Check that the pid file doesn't belong to a process that is still running.
+    fn check(path: &Path) -> Result<(), String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(format!("Could not read {}: {}", path.display(), error)),
+        };
+        let pid: libc::pid_t = content
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} does not contain a pid", path.display()))?;
+        if is_running(pid) {
+            return Err(format!(
+                "Already running with pid {} according to {}",
+                pid,
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            warn!("Could not remove {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+/// Check if a process with the given pid exists.
+///
+/// Signal 0 only performs the permission and existence checks. A process
+/// owned by another user still counts as running.
+fn is_running(pid: libc::pid_t) -> bool {
+    if pid <= 0 {
+        return false;
+    }
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Detach from the terminal and continue in a background process.
+///
+/// Standard output and error, and with them the logs, are redirected to
+/// `log_file` or discarded if it isn't given. Must be called before any
+/// threads are started.
+pub fn daemonize(log_file: Option<&Path>) -> Result<(), String> {
+    let output = match log_file {
+        Some(path) => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| format!("Could not open {}: {}", path.display(), error))?,
+        None => OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .map_err(|error| format!("Could not open /dev/null: {}", error))?,
+    };
+    let input =
+        File::open("/dev/null").map_err(|error| format!("Could not open /dev/null: {}", error))?;
+
+    fork_and_exit_parent()?;
+    if unsafe { libc::setsid() } < 0 {
+        return Err(format!(
+            "Could not create a new session: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    // Fork again so that the daemon is not a session leader and can never
+    // acquire a controlling terminal.
+    fork_and_exit_parent()?;
+
+    std::env::set_current_dir("/")
+        .map_err(|error| format!("Could not change directory to /: {}", error))?;
+    for (file, fd) in [
+        (&input, libc::STDIN_FILENO),
+        (&output, libc::STDOUT_FILENO),
+        (&output, libc::STDERR_FILENO),
+    ] {
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            return Err(format!(
+                "Could not redirect output: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Wait until the process is asked to terminate.
+pub async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("SIGTERM handler");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("SIGINT handler");
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+}
+
+fn fork_and_exit_parent() -> Result<(), String> {
+    match unsafe { libc::fork() } {
+        -1 => Err(format!(
+            "Could not fork: {}",
+            std::io::Error::last_os_error()
+        )),
+        0 => Ok(()),
+        _ => std::process::exit(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    #[test]
+    fn pid_file_check_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(PidFile::check(&dir.path().join("pagenine.pid")), Ok(()));
+    }
+
+    #[test]
+    fn pid_file_check_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+        assert!(PidFile::check(&path).is_err());
+    }
+
+    #[test]
+    fn pid_file_check_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, format!("{}\n", dead_pid())).unwrap();
+        assert_eq!(PidFile::check(&path), Ok(()));
+    }
+
+    #[test]
+    fn pid_file_check_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, "not a pid\n").unwrap();
+        assert!(PidFile::check(&path).is_err());
+    }
+
+    #[test]
+    fn pid_file_create_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        assert!(PidFile::create(&path).is_err());
+        pid_file.update().unwrap();
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn pid_file_create_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+        assert!(PidFile::create(&path).is_err());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn pid_file_create_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, format!("{}\n", dead_pid())).unwrap();
+        let _pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[test]
+    fn pid_file_create_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, "not a pid\n").unwrap();
+        assert!(PidFile::create(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a pid\n");
+    }
+
+    #[test]
+    fn is_running_invalid_pid() {
+        assert!(!is_running(0));
+        assert!(!is_running(-1));
+    }
+}
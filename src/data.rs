@@ -1,10 +1,25 @@
 use crate::pushover::PushoverClientTrait;
+use crate::velocity;
 use chrono::prelude::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug)]
 pub struct State {
     pub thread: Option<Thread>,
     pub notified: i32,
+    pub start_notified: bool,
+    /// Board the API redirected to, if it differs from the configured one.
+    pub board: Option<String>,
+    /// Recent positions of the thread for estimating the time until it is pruned.
+    pub history: velocity::History,
+    pub eta_notified: bool,
+    /// The thread was missing from the last catalog and is checked again
+    /// before it is considered dead.
+    pub missing: bool,
+    /// Last known state of the thread that died during this run.
+    pub died: Option<Thread>,
+    pub started: DateTime<Utc>,
+    pub notifications_sent: i32,
 }
 
 impl State {
@@ -12,11 +27,47 @@ impl State {
         State {
             thread: None,
             notified: 0,
+            start_notified: false,
+            board: None,
+            history: velocity::History::default(),
+            eta_notified: false,
+            missing: false,
+            died: None,
+            started: chrono::offset::Utc::now(),
+            notifications_sent: 0,
+        }
+    }
+
+    /// Add the current position of the thread to the history.
+    ///
+    /// A time-to-prune warning about another thread does not apply to this one.
+    pub fn record(&mut self, thread: &Thread) {
+        if self.history.is_other_thread(thread.no) {
+            self.eta_notified = false;
+        }
+        self.history.record(thread);
+    }
+
+    /// Forget the current thread while keeping track of run-wide events.
+    ///
+    /// The history and the time-to-prune warning are kept in case the same
+    /// thread is found again, for example after a failed fetch. They are
+    /// dropped once another thread is recorded.
+    pub fn reset(self) -> Self {
+        State {
+            start_notified: self.start_notified,
+            board: self.board,
+            history: self.history,
+            eta_notified: self.eta_notified,
+            died: self.died,
+            started: self.started,
+            notifications_sent: self.notifications_sent,
+            ..State::new()
         }
     }
 }
 
-#[derive(Clone, Default, Debug, PartialEq)]
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Thread {
     pub page: i32,
     pub no: i32,
@@ -24,6 +75,11 @@ pub struct Thread {
     pub time: DateTime<Utc>,
     pub position: i32,
     pub page_length: i32,
+    /// Position of the thread in the whole catalog, starting from 1.
+    pub slot: i32,
+    /// Number of threads in the whole catalog.
+    pub slot_count: i32,
+    pub stale: bool,
 }
 
 impl Thread {
@@ -55,39 +111,59 @@ impl Thread {
     pub async fn send_pushover_notification(
         &self,
         pushover_client: &impl PushoverClientTrait,
+        label: Option<&String>,
     ) -> Result<(), ()> {
         let message = format!(">page {}", self.page);
-        return pushover_client
-            .send_notification(message, Some(&self.sub))
-            .await;
+        match label {
+            Some(label) => {
+                let message = format!("{}\n{}", message, self.sub);
+                pushover_client
+                    .send_notification(message, Some(label))
+                    .await
+            }
+            None => {
+                pushover_client
+                    .send_notification(message, Some(&self.sub))
+                    .await
+            }
+        }
     }
 
-    #[cfg(target_os = "macos")]
-    pub fn show_notification(&self) -> Result<(), ()> {
+    /// Get the summary and body of an operating system notification.
+    ///
+    /// With a label, the label becomes the summary and the subject moves
+    /// to the body after the page number.
+    pub fn notification_text(&self, label: Option<&String>) -> (String, String) {
         let message = format!(">page {}", self.page);
-        let notification_handle = mac_notification_sys::Notification::default()
-            .title(message.as_str())
-            .message(self.sub.as_str())
-            .sound(mac_notification_sys::Sound::Default)
-            .send();
-        return match notification_handle {
-            Ok(_) => Ok(()),
-            Err(_) => Err(()),
-        };
+        match label {
+            Some(label) => (label.clone(), format!("{}\n{}", message, self.sub)),
+            None => (message, self.sub.clone()),
+        }
+    }
+
+    /// Print a notification about the thread to standard output.
+    pub fn print_notification(&self, label: Option<&String>) {
+        let (summary, body) = self.notification_text(label);
+        println!("{}: {}", summary, body.replace('\n', " - "));
     }
 
     /// Display a operating system notification about the thread.
-    #[cfg(not(target_os = "macos"))]
-    pub fn show_notification(&self) -> Result<(), ()> {
-        let message = format!(">page {}", self.page);
-        let notification_handle = notify_rust::Notification::new()
-            .summary(message.as_str())
-            .body(self.sub.as_str())
-            .show();
-        return match notification_handle {
-            Ok(_) => Ok(()),
-            Err(_) => Err(()),
-        };
+    pub fn show_notification(&self, label: Option<&String>) -> Result<(), ()> {
+        let (summary, body) = self.notification_text(label);
+        show_desktop_notification(&summary, &body, false)
+    }
+
+    /// Check if the thread is the next one to be pruned.
+    pub fn is_in_final_slot(&self) -> bool {
+        self.slot_count > 0 && self.slot >= self.slot_count
+    }
+
+    /// Describe where the thread currently is.
+    pub fn describe(&self, board: &String) -> String {
+        format!(
+            "{} on /{}/, currently page {} ({}/{})",
+            self.sub, board, self.page, self.position, self.page_length
+        )
     }
 
     /// Calculate how many full minutes since the refresh.
@@ -99,6 +175,56 @@ impl Thread {
     }
 }
 
+/// Display an operating system notification.
+///
+/// Low priority notifications are shown without a sound.
+#[cfg(target_os = "macos")]
+pub fn show_desktop_notification(summary: &str, body: &str, low_priority: bool) -> Result<(), ()> {
+    let mut notification = mac_notification_sys::Notification::default();
+    notification.title(summary).message(body);
+    if !low_priority {
+        notification.sound(mac_notification_sys::Sound::Default);
+    }
+    match notification.send() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(()),
+    }
+}
+
+/// Display an operating system notification.
+///
+/// Low priority notifications use the low urgency level where supported.
+#[cfg(not(target_os = "macos"))]
+pub fn show_desktop_notification(summary: &str, body: &str, low_priority: bool) -> Result<(), ()> {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(summary).body(body);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if low_priority {
+        notification.urgency(notify_rust::Urgency::Low);
+    }
+    #[cfg(not(unix))]
+    let _ = low_priority;
+    match notification.show() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(()),
+    }
+}
+
+/// Check that operating system notifications can be displayed.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn check_notification_server() -> Result<String, String> {
+    match notify_rust::get_server_information() {
+        Ok(info) => Ok(format!("{} {}", info.name, info.version)),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Check that operating system notifications can be displayed.
+#[cfg(any(target_os = "macos", windows))]
+pub fn check_notification_server() -> Result<String, String> {
+    Ok(String::from("provided by the operating system"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,6 +257,7 @@ mod tests {
             time: chrono::offset::Utc::now() - Duration::seconds(seconds),
             position: 1,
             page_length: 2,
+            ..Default::default()
         };
         assert_eq!(thread.check_if_needs_refresh(), needs_refresh);
     }
@@ -153,10 +280,111 @@ mod tests {
             time: chrono::offset::Utc::now() - Duration::seconds(seconds),
             position: position,
             page_length: 20,
+            ..Default::default()
         };
         assert_eq!(thread.check_if_needs_refresh(), needs_refresh);
     }
 
+    #[test]
+    fn state_reset() {
+        let mut history = velocity::History::default();
+        history.record(&Thread::default());
+        let state = State {
+            thread: Some(Thread::default()),
+            notified: 9,
+            start_notified: true,
+            board: Some(String::from("vg")),
+            history: history.clone(),
+            eta_notified: true,
+            missing: true,
+            died: Some(Thread::default()),
+            started: DateTime::from_timestamp(1700000000, 0).unwrap(),
+            notifications_sent: 3,
+        };
+        let state = state.reset();
+        assert!(state.thread.is_none());
+        assert_eq!(state.notified, 0);
+        assert!(state.start_notified);
+        assert_eq!(state.board, Some(String::from("vg")));
+        assert_eq!(state.history, history);
+        assert!(state.eta_notified);
+        assert!(!state.missing);
+        assert_eq!(state.died, Some(Thread::default()));
+        assert_eq!(
+            state.started,
+            DateTime::from_timestamp(1700000000, 0).unwrap()
+        );
+        assert_eq!(state.notifications_sent, 3);
+    }
+
+    #[test]
+    fn state_record() {
+        let mut state = State {
+            eta_notified: true,
+            ..State::new()
+        };
+        state.record(&Thread {
+            no: 1,
+            ..Default::default()
+        });
+        assert!(state.eta_notified);
+        state.record(&Thread {
+            no: 1,
+            slot: 2,
+            ..Default::default()
+        });
+        assert!(state.eta_notified);
+        state.record(&Thread {
+            no: 2,
+            ..Default::default()
+        });
+        assert!(!state.eta_notified);
+    }
+
+    #[test]
+    fn thread_describe() {
+        let thread = Thread {
+            page: 4,
+            no: 1,
+            sub: String::from("/xyz/ - Xyz General"),
+            time: chrono::offset::Utc::now(),
+            position: 7,
+            page_length: 15,
+            ..Default::default()
+        };
+        assert_eq!(
+            thread.describe(&String::from("vg")),
+            "/xyz/ - Xyz General on /vg/, currently page 4 (7/15)"
+        );
+    }
+
+    #[test]
+    fn thread_notification_text() {
+        let thread = Thread {
+            page: 9,
+            no: 1,
+            sub: String::from("/xyz/ - Xyz General #213 - midnight edition"),
+            time: chrono::offset::Utc::now(),
+            position: 1,
+            page_length: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            thread.notification_text(None),
+            (
+                String::from(">page 9"),
+                String::from("/xyz/ - Xyz General #213 - midnight edition")
+            )
+        );
+        assert_eq!(
+            thread.notification_text(Some(&String::from("XYZ"))),
+            (
+                String::from("XYZ"),
+                String::from(">page 9\n/xyz/ - Xyz General #213 - midnight edition")
+            )
+        );
+    }
+
     #[test_case(276, 4; "under closest minute")]
     #[test_case(300, 5; "even minute")]
     #[test_case(305, 5; "over closest minute")]
@@ -168,6 +396,7 @@ mod tests {
             time: chrono::offset::Utc::now() - Duration::seconds(seconds),
             position: 1,
             page_length: 2,
+            ..Default::default()
         };
         assert_eq!(thread.time_in_minutes(), minutes);
     }
@@ -0,0 +1,204 @@
+use crate::{api, client, data, notifier, pushover};
+
+const API_HOST: &str = "a.4cdn.org:443";
+
+/// Outcome of a single self-check.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub mandatory: bool,
+    pub result: Result<String, String>,
+}
+
+impl Check {
+    fn new(name: &'static str, mandatory: bool, result: Result<String, String>) -> Self {
+        Check {
+            name,
+            mandatory,
+            result,
+        }
+    }
+
+    fn skipped(name: &'static str, reason: &str) -> Self {
+        Check::new(name, false, Ok(format!("skipped, {}", reason)))
+    }
+
+    /// Format the check as a single line of the report.
+    pub fn line(&self) -> String {
+        let (status, detail) = match (&self.result, self.mandatory) {
+            (Ok(detail), _) => ("PASS", detail),
+            (Err(detail), true) => ("FAIL", detail),
+            (Err(detail), false) => ("WARN", detail),
+        };
+        format!("[{}] {}: {}", status, self.name, detail)
+    }
+}
+
+/// Check if all mandatory checks passed.
+pub fn passed(checks: &[Check]) -> bool {
+    checks
+        .iter()
+        .all(|check| !check.mandatory || check.result.is_ok())
+}
+
+/// Run all self-checks, printing each result as soon as it is known.
+pub async fn run(
+    client: &reqwest::Client,
+    ip_family: Option<client::IpFamily>,
+    board: Option<&String>,
+    title: Option<&String>,
+    pushover_client: Option<&pushover::PushoverClient>,
+    desktop_required: bool,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut report = |check: Check| {
+        println!("{}", check.line());
+        checks.push(check);
+    };
+
+    report(Check::new("DNS", true, check_dns().await));
+    if let Some(ip_family) = ip_family {
+        let result = client::check_route(ip_family)
+            .await
+            .map(|_| format!("{} route available", ip_family));
+        report(Check::new("Route", true, result));
+    }
+
+    let boards = api::Boards::fetch(client).await;
+    report(Check::new(
+        "HTTPS",
+        true,
+        match &boards {
+            Ok(boards) => Ok(format!("fetched {} boards", boards.boards.len())),
+            Err(error) => Err(error.to_string()),
+        },
+    ));
+
+    match (board, &boards) {
+        (Some(board), Ok(boards)) => {
+            let result = match boards.find(board) {
+                Some(found) => Ok(format!("/{}/ - {}", found.board, found.title)),
+                None => Err(format!("/{}/ does not exist", board)),
+            };
+            report(Check::new("Board", true, result));
+        }
+        (Some(_), Err(_)) => report(Check::new(
+            "Board",
+            true,
+            Err(String::from("board list unavailable")),
+        )),
+        (None, _) => report(Check::skipped("Board", "no board given")),
+    }
+
+    match (board, title) {
+        (Some(board), Some(title)) => {
+            let result = match api::Catalog::fetch(client, board, false).await {
+                Ok(fetched) => describe_matches(title, fetched.catalog.find_all(title)),
+                Err(error) => Err(error.to_string()),
+            };
+            report(Check::new("Title", false, result));
+        }
+        _ => report(Check::skipped("Title", "no board and title given")),
+    }
+
+    let notifications = notifier::probe_desktop()
+        .await
+        .map_err(|headless| headless.to_string());
+    report(Check::new(
+        "Desktop notifications",
+        desktop_required,
+        notifications,
+    ));
+
+    match pushover_client {
+        Some(pushover_client) => {
+            let result = pushover_client
+                .validate()
+                .await
+                .map(|_| String::from("credentials accepted"));
+            report(Check::new("Pushover", true, result));
+        }
+        None => report(Check::skipped("Pushover", "not configured")),
+    }
+
+    checks
+}
+
+async fn check_dns() -> Result<String, String> {
+    match tokio::net::lookup_host(API_HOST).await {
+        Ok(addresses) => {
+            let addresses: Vec<String> =
+                addresses.map(|address| address.ip().to_string()).collect();
+            Ok(format!("{} resolves to {}", API_HOST, addresses.join(", ")))
+        }
+        Err(error) => Err(format!("could not resolve {}: {}", API_HOST, error)),
+    }
+}
+
+fn describe_matches(title: &String, threads: Vec<data::Thread>) -> Result<String, String> {
+    if threads.is_empty() {
+        return Err(format!("no thread matches \"{}\"", title));
+    }
+    let candidates: Vec<String> = threads
+        .iter()
+        .map(|thread| {
+            format!(
+                "\"{}\" (No. {}, page {})",
+                thread.sub, thread.no, thread.page
+            )
+        })
+        .collect();
+    Ok(candidates.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    fn make_thread(no: i32, page: i32) -> data::Thread {
+        data::Thread {
+            page,
+            no,
+            sub: format!("/xyz/ #{}", no),
+            time: chrono::offset::Utc::now(),
+            position: 1,
+            page_length: 10,
+            slot: (page - 1) * 10 + 1,
+            slot_count: 100,
+            stale: false,
+        }
+    }
+
+    #[test_case(Ok(String::from("ok")), true, "[PASS] Test: ok"; "pass")]
+    #[test_case(Err(String::from("bad")), true, "[FAIL] Test: bad"; "fail")]
+    #[test_case(Err(String::from("bad")), false, "[WARN] Test: bad"; "warn")]
+    fn check_line(result: Result<String, String>, mandatory: bool, line: &str) {
+        assert_eq!(Check::new("Test", mandatory, result).line(), line);
+    }
+
+    #[test]
+    fn checks_passed() {
+        let mut checks = vec![
+            Check::new("A", true, Ok(String::new())),
+            Check::new("B", false, Err(String::new())),
+            Check::skipped("C", "not configured"),
+        ];
+        assert!(passed(&checks));
+        checks.push(Check::new("D", true, Err(String::new())));
+        assert!(!passed(&checks));
+    }
+
+    #[test]
+    fn describe_matches_candidates() {
+        let title = String::from("/xyz/");
+        assert_eq!(
+            describe_matches(&title, vec![make_thread(1, 3), make_thread(2, 9)]),
+            Ok(String::from(
+                "\"/xyz/ #1\" (No. 1, page 3); \"/xyz/ #2\" (No. 2, page 9)"
+            ))
+        );
+        assert!(describe_matches(&title, Vec::new()).is_err());
+    }
+}
@@ -1,96 +1,634 @@
 use chrono::prelude::{DateTime, Utc};
-use clap::Parser;
-use log::{info, warn, LevelFilter};
+use clap::{Parser, Subcommand};
+use log::{error, info, warn, LevelFilter};
 use simple_logger::SimpleLogger;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
 use std::time::Duration;
 use tokio::{task, time};
 
 mod api;
+mod budget;
+mod client;
+mod config;
+#[cfg(unix)]
+mod daemon;
 mod data;
+mod doctor;
+mod notifier;
 mod pushover;
+mod state_file;
+mod velocity;
+
+/// Exit code used when `--exit-on-death` ends the run.
+const EXIT_THREAD_DIED: i32 = 3;
 
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct PagenineArgs {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Configuration file to read settings from.
+    ///
+    /// Defaults to pagenine/config.toml in the user configuration directory.
+    #[clap(long, value_parser, global = true)]
+    pub config: Option<PathBuf>,
+
     /// Name of the board to scan.
     #[clap(value_parser = validate_board)]
-    pub board: String,
+    pub board: Option<String>,
 
-    /// Title of the thread to scan.
-    #[clap(value_parser)]
-    pub title: String,
+    /// Title of the thread to scan, or `-` to read it from standard input.
+    #[clap(value_parser, conflicts_with = "title_file")]
+    pub title: Option<String>,
 
-    /// Pushover application API key.
+    /// Read the title of the thread to scan from a file, or `-` for standard input.
     #[clap(long, value_parser)]
+    pub title_file: Option<PathBuf>,
+
+    /// Short name used as the notification title and in log lines.
+    #[clap(long, value_parser)]
+    pub label: Option<String>,
+
+    /// Pushover application API key.
+    #[clap(long, value_parser, global = true)]
     pub pushover_application_api_token: Option<String>,
 
-    /// Pushover user key.
+    /// Pushover user or delivery group key. Separate several keys with commas or repeat the option.
+    #[clap(long, value_parser, value_delimiter = ',', global = true)]
+    pub pushover_user_key: Vec<String>,
+
+    /// How to deliver notifications [default: pushover if configured, otherwise desktop]
+    #[clap(long, value_enum, global = true)]
+    pub notifier: Option<notifier::NotifierKind>,
+
+    /// Use desktop notifications even if the environment looks headless.
+    #[clap(long, value_parser)]
+    pub force_desktop: bool,
+
+    /// Send a notification confirming the watched thread after the first check.
+    #[clap(long, value_parser)]
+    pub notify_start: bool,
+
+    /// Also notify when the thread is estimated to reach the final slot within this many minutes.
+    #[clap(long, value_parser = clap::value_parser!(i64).range(1..))]
+    pub eta_threshold: Option<i64>,
+
+    /// After the watched thread dies, move on to a newer thread with the same title (default).
+    #[clap(long, value_parser)]
+    pub follow: bool,
+
+    /// Exit with code 3 after the watched thread dies instead of moving on to a newer one.
+    #[clap(long, value_parser, conflicts_with = "follow")]
+    pub exit_on_death: bool,
+
+    /// Hold back notifications over this many per hour and send them as a single digest.
+    /// Notifications about the final slot and dead threads are always sent.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_notifications_per_hour: Option<u32>,
+
+    /// Save the watch state to this file and pick up from it after a restart.
+    #[clap(long, value_parser)]
+    pub state_file: Option<PathBuf>,
+
+    /// Minutes without any thread activity after which the catalog is considered stale.
+    #[clap(long, value_parser = clap::value_parser!(i64).range(1..), default_value_t = 5)]
+    pub stale_threshold: i64,
+
+    /// Refetch a stale catalog once, bypassing the CDN cache.
+    #[clap(long, value_parser)]
+    pub stale_retry: bool,
+
+    /// Only connect over IPv4.
+    #[clap(long, value_parser, conflicts_with = "ipv6", global = true)]
+    pub ipv4: bool,
+
+    /// Only connect over IPv6.
+    #[clap(long, value_parser, global = true)]
+    pub ipv6: bool,
+
+    /// Fork into the background after validating the configuration.
+    #[cfg(unix)]
+    #[clap(long, value_parser)]
+    pub daemon: bool,
+
+    /// Write the process id to this file and remove it when exiting.
+    #[cfg(unix)]
     #[clap(long, value_parser)]
-    pub pushover_user_key: Option<String>,
+    pub pid_file: Option<PathBuf>,
+
+    /// Append logs to this file when running as a daemon.
+    #[cfg(unix)]
+    #[clap(long, value_parser, requires = "daemon")]
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the network, board, title and notifiers for problems.
+    Doctor {
+        /// Name of the board to check.
+        #[clap(value_parser = validate_board)]
+        board: Option<String>,
+
+        /// Title of the thread to look for.
+        #[clap(value_parser)]
+        title: Option<String>,
+    },
+}
+
+impl PagenineArgs {
+    /// Fill settings missing from the command line from the configuration file.
+    pub fn apply_config(&mut self, config: config::Config) {
+        if self.board.is_none() {
+            self.board = config
+                .board
+                .map(|board| board.trim_matches('/').to_string());
+        }
+        if self.title.is_none() && self.title_file.is_none() {
+            self.title = config.title;
+        }
+        self.label = self.label.take().or(config.label);
+        self.pushover_application_api_token = self
+            .pushover_application_api_token
+            .take()
+            .or(config.pushover_application_api_token);
+        if self.pushover_user_key.is_empty() {
+            self.pushover_user_key = config
+                .pushover_user_key
+                .map(|keys| keys.split(',').map(String::from).collect())
+                .unwrap_or_default();
+        }
+        self.notifier = self.notifier.or(config.notifier);
+        self.state_file = self.state_file.take().or(config.state_file);
+        #[cfg(unix)]
+        {
+            self.log_file = self.log_file.take().or(config.log_file);
+        }
+    }
+
+    /// Check that a board and title were given on the command line or in
+    /// the configuration file.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.board.is_none() {
+            return Err(String::from(
+                "No board given on the command line or in the configuration file",
+            ));
+        }
+        if self.title.is_none() && self.title_file.is_none() {
+            return Err(String::from(
+                "No title given on the command line or in the configuration file",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the name of the board to scan.
+    pub fn board(&self) -> &String {
+        self.board
+            .as_ref()
+            .expect("board should be validated during startup")
+    }
+
+    /// Get the prefix that identifies this watch in log lines.
+    pub fn log_prefix(&self) -> String {
+        match &self.label {
+            Some(label) => format!("[{}] ", label),
+            None => String::new(),
+        }
+    }
+
+    /// Get the title of the thread to scan.
+    pub fn title(&self) -> &String {
+        self.title
+            .as_ref()
+            .expect("title should be resolved during startup")
+    }
+
+    /// Replace a title given as a file or standard input with its content.
+    pub fn resolve_title(&mut self) -> Result<(), String> {
+        let title = match (&self.title_file, self.title.as_deref()) {
+            (Some(path), _) if path.as_os_str() == "-" => read_title(std::io::stdin()),
+            (Some(path), _) => {
+                let file = File::open(path)
+                    .map_err(|error| format!("Could not open {}: {}", path.display(), error))?;
+                read_title(file)
+            }
+            (None, Some("-")) => read_title(std::io::stdin()),
+            _ => return Ok(()),
+        }?;
+        self.title = Some(title);
+        Ok(())
+    }
+
+    /// Get the notifier that will be used to deliver notifications.
+    pub fn notifier_kind(&self) -> Result<notifier::NotifierKind, String> {
+        let pushover_configured =
+            self.pushover_application_api_token.is_some() && !self.pushover_user_keys().is_empty();
+        match self.notifier {
+            Some(notifier::NotifierKind::Pushover) if !pushover_configured => Err(String::from(
+                "Pushover requires --pushover-application-api-token and --pushover-user-key",
+            )),
+            Some(kind) => Ok(kind),
+            None if pushover_configured => Ok(notifier::NotifierKind::Pushover),
+            None => Ok(notifier::NotifierKind::Desktop),
+        }
+    }
+
+    /// Get the Pushover user keys without surrounding whitespace or empty entries.
+    pub fn pushover_user_keys(&self) -> Vec<String> {
+        self.pushover_user_key
+            .iter()
+            .map(|key| key.trim())
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Get the IP address family selected with the command line flags.
+    pub fn ip_family(&self) -> Option<client::IpFamily> {
+        match (self.ipv4, self.ipv6) {
+            (true, _) => Some(client::IpFamily::V4),
+            (_, true) => Some(client::IpFamily::V6),
+            _ => None,
+        }
+    }
+}
+
+/// Read a thread title, dropping the trailing newline that files usually end in.
+fn read_title(mut reader: impl Read) -> Result<String, String> {
+    let mut content = String::new();
+    reader
+        .read_to_string(&mut content)
+        .map_err(|error| format!("Could not read title: {}", error))?;
+    let title = content
+        .strip_suffix("\r\n")
+        .or_else(|| content.strip_suffix('\n'))
+        .unwrap_or(&content);
+    if title.is_empty() {
+        return Err(String::from("Title must not be empty"));
+    }
+    Ok(title.to_string())
 }
 
 fn validate_board(value: &str) -> Result<String, String> {
     Ok(value.trim_matches('/').to_string())
 }
 
+/// Thread found in a freshly fetched catalog.
+#[derive(Debug, PartialEq)]
+struct Lookup {
+    /// The watched thread, or the first thread with the matching title if
+    /// nothing is watched or the watched thread is missing.
+    thread: Option<data::Thread>,
+    /// The previously watched thread is no longer in the catalog.
+    watched_missing: bool,
+}
+
+/// Fetch the catalog and find the thread in it.
+///
+/// Returns `None` if the catalog has not been modified since
+/// `if_modified_since`. If the API redirects to another board, `board` is
+/// updated to it so that later requests go there directly.
 async fn get_current_thread(
-    board: &String,
-    title: &String,
+    args: &PagenineArgs,
+    client: &reqwest::Client,
+    board: &mut String,
     if_modified_since: Option<DateTime<Utc>>,
-) -> Option<data::Thread> {
-    let catalog = match api::Catalog::fetch(board, if_modified_since).await {
-        Ok(catalog) => catalog,
+    watched: Option<i32>,
+) -> Result<Option<Lookup>, ()> {
+    let threshold = chrono::Duration::minutes(args.stale_threshold);
+    let mut fetched = match api::Catalog::fetch_if_modified(client, board, if_modified_since).await
+    {
+        Ok(Some(fetched)) => fetched,
+        Ok(None) => return Ok(None),
         Err(error) => {
-            warn!("{}", error);
-            return None;
+            warn!("{}{}", args.log_prefix(), error);
+            return Err(());
         }
     };
-    catalog.find(title)
+    if fetched.is_stale(threshold) && args.stale_retry {
+        info!("{}Catalog looks stale, refetching", args.log_prefix());
+        match api::Catalog::fetch(client, &fetched.board, true).await {
+            Ok(fresh) => fetched = fresh,
+            Err(error) => warn!("{}{}", args.log_prefix(), error),
+        }
+    }
+    let stale = fetched.is_stale(threshold);
+    *board = fetched.board;
+    let catalog = fetched.catalog;
+
+    if stale {
+        warn!(
+            "{}Catalog has not been modified in over {} minutes, results may be outdated",
+            args.log_prefix(),
+            args.stale_threshold
+        );
+    }
+    let lookup = find_thread(args, &catalog, watched);
+    Ok(Some(Lookup {
+        thread: lookup.thread.map(|thread| data::Thread { stale, ..thread }),
+        ..lookup
+    }))
 }
 
-async fn check(
+/// Find the thread to watch in the catalog.
+///
+/// Once a thread is watched, it is followed by its number so that a new
+/// thread with the same title doesn't take over while the old one is still
+/// alive. The title is only used to find a thread to start with or to
+/// replace a missing one.
+fn find_thread(args: &PagenineArgs, catalog: &api::Catalog, watched: Option<i32>) -> Lookup {
+    let thread = watched.and_then(|no| catalog.find_no(no));
+    Lookup {
+        watched_missing: watched.is_some() && thread.is_none(),
+        thread: thread.or_else(|| catalog.find(args.title())),
+    }
+}
+
+/// What happened to the saved thread while pagenine was not running.
+#[derive(Debug, PartialEq)]
+enum Offline {
+    /// The thread can be watched like before.
+    Alive(data::Thread),
+    /// The thread died, and another thread may have taken its title.
+    Died {
+        thread: data::Thread,
+        archived_on: Option<DateTime<Utc>>,
+        replacement: Option<data::Thread>,
+    },
+}
+
+/// Work out what happened to the saved thread from the current catalog and
+/// what the API says about the thread.
+fn reconcile_thread(
+    args: &PagenineArgs,
+    saved: data::Thread,
+    catalog: &api::Catalog,
+    status: api::ThreadStatus,
+) -> Offline {
+    if let Some(thread) = catalog.find_no(saved.no) {
+        return Offline::Alive(thread);
+    }
+    let archived_on = match status {
+        // Probably just missing from a cached catalog, so the normal death
+        // detection gets to decide.
+        api::ThreadStatus::Open => return Offline::Alive(saved),
+        api::ThreadStatus::Archived(archived_on) => Some(archived_on),
+        api::ThreadStatus::Gone => None,
+    };
+    let replacement = catalog
+        .find(args.title())
+        .filter(|thread| thread.no != saved.no);
+    Offline::Died {
+        thread: saved,
+        archived_on,
+        replacement,
+    }
+}
+
+/// Restore the state saved by an earlier run and catch up with what happened
+/// to the thread since.
+async fn resume(
+    args: &PagenineArgs,
+    client: &reqwest::Client,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+    state_file: state_file::StateFile,
+) -> data::State {
+    resume_from(args, client, api::API_URL, notifier, state_file).await
+}
+
+async fn resume_from(
     args: &PagenineArgs,
-    pushover_client: &Option<impl pushover::PushoverClientTrait>,
+    client: &reqwest::Client,
+    api_url: &str,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+    state_file: state_file::StateFile,
+) -> data::State {
+    let state = state_file.to_state();
+    let saved = match state_file.thread {
+        Some(saved) => saved,
+        None => return state,
+    };
+    let board = effective_board(args, &state).clone();
+    let fetched = match api::Catalog::fetch_at(client, api_url, &board, false).await {
+        Ok(fetched) => fetched,
+        Err(error) => {
+            warn!("{}{}", args.log_prefix(), error);
+            return data::State {
+                thread: Some(saved),
+                ..state
+            };
+        }
+    };
+    let state = data::State {
+        board: Some(fetched.board.clone()).filter(|board| board != args.board()),
+        ..state
+    };
+    let status = match fetched.catalog.contains(saved.no) {
+        true => api::ThreadStatus::Open,
+        false => {
+            match api::ThreadStatus::fetch_from(client, api_url, &fetched.board, saved.no).await {
+                Ok(status) => status,
+                Err(error) => {
+                    warn!("{}{}", args.log_prefix(), error);
+                    api::ThreadStatus::Open
+                }
+            }
+        }
+    };
+    let offline = reconcile_thread(args, saved, &fetched.catalog, status);
+    let mut state = report_offline(state, offline, args, notifier).await;
+    // The resumed thread has just been fetched, so it is notified about like
+    // after a check instead of waiting for the next modified catalog.
+    if let Some(thread) = state.thread.clone() {
+        if args.notify_start {
+            state = notify_start(state, Some(&thread), args, notifier).await;
+        }
+        state = notify(state, thread, args.label.as_ref(), notifier).await;
+    }
+    state
+}
+
+/// Continue watching the saved thread or report that it died.
+async fn report_offline(
     state: data::State,
+    offline: Offline,
+    args: &PagenineArgs,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+) -> data::State {
+    let (thread, archived_on, replacement) = match offline {
+        Offline::Alive(thread) => {
+            info!(
+                "{}Resuming {}",
+                args.log_prefix(),
+                thread.describe(effective_board(args, &state))
+            );
+            return data::State {
+                thread: Some(thread),
+                ..state
+            };
+        }
+        Offline::Died {
+            thread,
+            archived_on,
+            replacement,
+        } => (thread, archived_on, replacement),
+    };
+
+    let mut message = match archived_on {
+        Some(archived_on) => format!(
+            "Thread died while pagenine was off (archived at {})",
+            archived_on.format("%Y-%m-%d %H:%M UTC")
+        ),
+        None => String::from("Thread died while pagenine was off"),
+    };
+    info!("{}\"{}\": {}", args.log_prefix(), thread.sub, message);
+    message = format!("{}\n{}", message, thread.sub);
+    if let Some(replacement) = replacement {
+        message = format!(
+            "{}\n{}: {}",
+            message,
+            match args.exit_on_death {
+                true => "Newer thread",
+                false => "Now watching",
+            },
+            replacement.describe(effective_board(args, &state))
+        );
+    }
+    let title = args.label.clone().unwrap_or_else(|| String::from(">dead"));
+    let notifications_sent = match notifier
+        .send_alert(&title, message, notifier::Severity::Critical)
+        .await
+    {
+        Ok(delivery) => state.notifications_sent + delivery.count(),
+        Err(_) => state.notifications_sent,
+    };
+    data::State {
+        died: Some(thread),
+        eta_notified: false,
+        notifications_sent,
+        ..state
+    }
+    .reset()
+}
+
+/// Save the state if a state file is used and it changed since `saved`,
+/// the state that was last saved.
+fn save_state(
+    args: &PagenineArgs,
+    state: &data::State,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+    saved: &mut Option<state_file::StateFile>,
+) {
+    if let Some(path) = &args.state_file {
+        let state_file = state_file::StateFile {
+            budget: notifier.budget(),
+            ..state_file::StateFile::new(args.board(), args.title(), state)
+        };
+        if saved
+            .as_ref()
+            .is_some_and(|saved| saved.same_watch(&state_file))
+        {
+            return;
+        }
+        match state_file.save(path) {
+            Ok(_) => *saved = Some(state_file),
+            Err(error) => warn!("{}", error),
+        }
+    }
+}
+
+/// Get the board to use for requests, following any earlier redirect.
+fn effective_board<'a>(args: &'a PagenineArgs, state: &'a data::State) -> &'a String {
+    state.board.as_ref().unwrap_or(args.board())
+}
+
+async fn check(
+    args: &PagenineArgs,
+    client: &reqwest::Client,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+    mut state: data::State,
 ) -> data::State {
-    let refresh = state
-        .thread
-        .as_ref()
-        .map_or(true, |thread| thread.check_if_needs_refresh());
+    let refresh = state.missing
+        || state
+            .thread
+            .as_ref()
+            .map_or(true, |thread| thread.check_if_needs_refresh());
 
     let thread = if refresh {
         let last_update_time = state.thread.as_ref().map(|thread| thread.time);
-        get_current_thread(&args.board, &args.title, last_update_time).await
+        let watched = state.thread.as_ref().map(|thread| thread.no);
+        let mut board = effective_board(args, &state).clone();
+        let result = get_current_thread(args, client, &mut board, last_update_time, watched).await;
+        state.board = Some(board).filter(|board| board != args.board());
+        let lookup = match result {
+            Ok(Some(lookup)) => lookup,
+            Ok(None) => {
+                // Nothing has moved since the last fetch, so neither has the
+                // thread. Only the time of the check is updated so that the
+                // next one is not due right away.
+                info!("{}Catalog has not been modified", args.log_prefix());
+                if let Some(thread) = &mut state.thread {
+                    thread.time = Utc::now();
+                }
+                return state;
+            }
+            Err(_) => return state,
+        };
+        state = match check_death(state, lookup.watched_missing, args, notifier).await {
+            Ok(state) => state,
+            Err(state) => return state,
+        };
+        let thread = lookup.thread;
+        if args.notify_start {
+            state = notify_start(state, thread.as_ref(), args, notifier).await;
+        }
+        if let Some(thread) = &thread {
+            state.record(thread);
+            state = notify_eta(state, thread, args, notifier).await;
+        }
+        thread
     } else {
         state.thread.clone()
     };
     let thread = match thread {
         Some(thread) => thread,
-        None => return data::State::new(),
+        None => return state.reset(),
     };
 
     if refresh {
         info!(
-            "\"{}\", page {} ({}/{})",
-            thread.sub, thread.page, thread.position, thread.page_length
+            "{}\"{}\", page {} ({}/{}){}",
+            args.log_prefix(),
+            thread.sub,
+            thread.page,
+            thread.position,
+            thread.page_length,
+            if thread.stale { " [stale]" } else { "" }
         );
     }
 
-    return notify(state, thread, pushover_client).await;
+    return notify(state, thread, args.label.as_ref(), notifier).await;
 }
 
 async fn notify(
     state: data::State,
     thread: data::Thread,
-    pushover_client: &Option<impl pushover::PushoverClientTrait>,
+    label: Option<&String>,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
 ) -> data::State {
     let mut notified = state.notified;
+    let mut notifications_sent = state.notifications_sent;
     if thread.page >= 9 && thread.page != state.notified {
-        let notification_shown = match pushover_client {
-            Some(pushover_client) => thread.send_pushover_notification(pushover_client).await,
-            None => thread.show_notification(),
-        };
+        let notification_shown = notifier.send(&thread, label).await;
         notified = match notification_shown {
-            Ok(_) => thread.page,
+            Ok(delivery) => {
+                notifications_sent += delivery.count();
+                thread.page
+            }
             Err(_) => state.notified,
         }
     } else if thread.page < 9 {
@@ -99,39 +637,414 @@ async fn notify(
     return data::State {
         thread: Some(thread),
         notified,
+        notifications_sent,
+        ..state
+    };
+}
+
+/// Handle the watched thread disappearing from the catalog.
+///
+/// A thread missing once might be a glitch in the catalog, so it is only
+/// considered dead when it is still missing from the next one. Until then
+/// the state is returned as an error to skip the rest of the check, and so
+/// it is after the death with `--exit-on-death`. Fetch failures never get
+/// here.
+async fn check_death(
+    state: data::State,
+    watched_missing: bool,
+    args: &PagenineArgs,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+) -> Result<data::State, data::State> {
+    let thread = match state.thread.clone() {
+        Some(thread) if watched_missing => thread,
+        _ => {
+            return Ok(data::State {
+                missing: false,
+                ..state
+            })
+        }
+    };
+    if !state.missing {
+        info!(
+            "{}\"{}\" is no longer in the catalog, checking again",
+            args.log_prefix(),
+            thread.sub
+        );
+        return Err(data::State {
+            missing: true,
+            ..state
+        });
+    }
+
+    info!("{}\"{}\" has died", args.log_prefix(), thread.sub);
+    let title = args.label.clone().unwrap_or_else(|| String::from(">dead"));
+    let message = format!("Thread died after page {}\n{}", thread.page, thread.sub);
+    let notifications_sent = match notifier
+        .send_alert(&title, message, notifier::Severity::Critical)
+        .await
+    {
+        Ok(delivery) => state.notifications_sent + delivery.count(),
+        Err(_) => state.notifications_sent,
+    };
+    let state = data::State {
+        died: Some(thread),
+        eta_notified: false,
+        notifications_sent,
+        ..state
+    }
+    .reset();
+    match args.exit_on_death {
+        true => Err(state),
+        false => Ok(state),
+    }
+}
+
+/// Get the exit code if the run is over.
+///
+/// Only `--exit-on-death` ends the run, otherwise a newer thread is watched.
+fn finished(args: &PagenineArgs, state: &data::State) -> Option<i32> {
+    match state.died {
+        Some(_) if args.exit_on_death => Some(EXIT_THREAD_DIED),
+        _ => None,
+    }
+}
+
+/// Describe what happened during the run when exiting.
+fn summary(args: &PagenineArgs, state: &data::State, now: DateTime<Utc>) -> String {
+    let watched = now - state.started;
+    let mut lines = Vec::new();
+    if let Some(thread) = &state.died {
+        lines.push(format!(
+            "\"{}\" (No. {}) on /{}/ died after page {} ({}/{})",
+            thread.sub,
+            thread.no,
+            effective_board(args, state),
+            thread.page,
+            thread.position,
+            thread.page_length
+        ));
+    }
+    lines.push(format!(
+        "Watched for {}h {}min, {} notifications sent",
+        watched.num_hours(),
+        watched.num_minutes() % 60,
+        state.notifications_sent
+    ));
+    lines.join("\n")
+}
+
+/// Warn once when the thread is estimated to be pruned within the threshold.
+///
+/// The warning is sent again only after the estimate has recovered, which
+/// usually means the thread was bumped.
+async fn notify_eta(
+    state: data::State,
+    thread: &data::Thread,
+    args: &PagenineArgs,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+) -> data::State {
+    let threshold = match args.eta_threshold {
+        Some(minutes) => chrono::Duration::minutes(minutes),
+        None => return state,
+    };
+    let eta = state.history.eta(thread.slot_count);
+    match velocity::evaluate(eta, threshold, state.eta_notified) {
+        velocity::EtaAction::Notify => {
+            let message = format!(
+                "About {} minutes until the last page\n{}",
+                eta.map_or(0, |eta| eta.num_minutes()),
+                thread.sub
+            );
+            let title = args
+                .label
+                .clone()
+                .unwrap_or_else(|| format!(">page {}", thread.page));
+            match notifier
+                .send_alert(&title, message, notifier::Severity::Normal)
+                .await
+            {
+                Ok(delivery) => data::State {
+                    eta_notified: true,
+                    notifications_sent: state.notifications_sent + delivery.count(),
+                    ..state
+                },
+                Err(_) => state,
+            }
+        }
+        velocity::EtaAction::Rearm => data::State {
+            eta_notified: false,
+            ..state
+        },
+        velocity::EtaAction::Keep => state,
+    }
+}
+
+/// Confirm what is being watched once the first catalog has been fetched.
+///
+/// The confirmation is sent at most once per run and does not affect the
+/// page notifications.
+async fn notify_start(
+    state: data::State,
+    thread: Option<&data::Thread>,
+    args: &PagenineArgs,
+    notifier: &notifier::Notifier<impl pushover::PushoverClientTrait>,
+) -> data::State {
+    if state.start_notified {
+        return state;
+    }
+    let message = match thread {
+        Some(thread) => format!(
+            "Watching: {}",
+            thread.describe(effective_board(args, &state))
+        ),
+        None => format!(
+            "Watching /{}/, no thread matching \"{}\" found yet",
+            effective_board(args, &state),
+            args.title()
+        ),
+    };
+    let title = args
+        .label
+        .clone()
+        .unwrap_or_else(|| String::from("pagenine"));
+    match notifier.send_status(&title, message).await {
+        Ok(delivery) => data::State {
+            start_notified: true,
+            notifications_sent: state.notifications_sent + delivery.count(),
+            ..state
+        },
+        Err(_) => state,
+    }
+}
+
+fn make_pushover_client(
+    args: &PagenineArgs,
+    client: &reqwest::Client,
+) -> Option<pushover::PushoverClient> {
+    let users = args.pushover_user_keys();
+    match &args.pushover_application_api_token {
+        Some(token) if !users.is_empty() => Some(pushover::PushoverClient {
+            client: client.clone(),
+            token: token.to_string(),
+            users,
+        }),
+        _ => None,
+    }
+}
+
+/// Create the pid file and fork into the background if requested.
+///
+/// The pid file is created before forking so that a second instance fails
+/// while it can still report the error, and updated with the pid of the
+/// daemon afterwards.
+#[cfg(unix)]
+fn start_daemon(args: &PagenineArgs) -> Result<Option<daemon::PidFile>, String> {
+    // The daemon changes its working directory, so relative paths must be
+    // resolved beforehand.
+    let pid_file = match &args.pid_file {
+        Some(path) => {
+            let current_dir = std::env::current_dir().map_err(|error| error.to_string())?;
+            Some(daemon::PidFile::create(&current_dir.join(path))?)
+        }
+        None => None,
     };
+    if args.daemon {
+        daemon::daemonize(args.log_file.as_deref())?;
+        if let Some(pid_file) = &pid_file {
+            pid_file.update()?;
+        }
+    }
+    Ok(pid_file)
+}
+
+/// Check that the network and the notifications can be used.
+///
+/// This runs before forking so that a misconfigured daemon fails in the
+/// terminal instead of only in the log file.
+async fn preflight(args: &PagenineArgs) -> Result<(), String> {
+    let notifier_kind = args.notifier_kind()?;
+    if let Some(ip_family) = args.ip_family() {
+        client::check_route(ip_family).await?;
+    }
+    if notifier_kind == notifier::NotifierKind::Desktop && !args.force_desktop {
+        if let Err(headless) = notifier::probe_desktop().await {
+            return Err(format!(
+                "Desktop notifications are unavailable ({}). Use `--notifier stdout` or configure Pushover, or pass `--force-desktop` to try anyway.",
+                headless
+            ));
+        }
+    }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() {
+fn main() {
+    let mut args = PagenineArgs::parse();
+    #[cfg(unix)]
+    let colors = !args.daemon;
+    #[cfg(not(unix))]
+    let colors = true;
     SimpleLogger::new()
         .with_level(LevelFilter::Info)
+        .with_colors(colors)
         .env()
         .init()
         .unwrap();
-    let args = PagenineArgs::parse();
+    match config::Config::discover(args.config.as_deref()) {
+        Ok(Some((path, config))) => {
+            info!("Using configuration file {}", path.display());
+            args.apply_config(config);
+        }
+        Ok(None) => {}
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    if args.command.is_none() {
+        if let Err(error) = args.validate().and_then(|_| args.resolve_title()) {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    // Resolve the state file before the daemon changes its working directory.
+    if let Some(path) = &args.state_file {
+        match std::env::current_dir() {
+            Ok(current_dir) => args.state_file = Some(current_dir.join(path)),
+            Err(error) => {
+                error!("Could not resolve {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.command.is_none() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        if let Err(error) = runtime.block_on(preflight(&args)) {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(unix)]
+    let pid_file = match start_daemon(&args) {
+        Ok(pid_file) => pid_file,
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    #[cfg(unix)]
+    let handle_signals = pid_file.is_some();
+    #[cfg(not(unix))]
+    let handle_signals = false;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let exit_code = runtime.block_on(run(args, handle_signals));
+    #[cfg(unix)]
+    drop(pid_file);
+    std::process::exit(exit_code);
+}
+
+async fn run(args: PagenineArgs, handle_signals: bool) -> i32 {
+    let client = match client::build(args.ip_family()) {
+        Ok(client) => client,
+        Err(error) => {
+            error!("Could not create HTTP client: {}", error);
+            return 1;
+        }
+    };
+    let pushover_client = make_pushover_client(&args, &client);
+    let notifier_kind = match args.notifier_kind() {
+        Ok(notifier_kind) => notifier_kind,
+        Err(error) => {
+            error!("{}", error);
+            return 1;
+        }
+    };
+
+    if let Some(Command::Doctor { board, title }) = &args.command {
+        let checks = doctor::run(
+            &client,
+            args.ip_family(),
+            board.as_ref().or(args.board.as_ref()),
+            title.as_ref().or(args.title.as_ref()),
+            pushover_client.as_ref(),
+            notifier_kind == notifier::NotifierKind::Desktop,
+        )
+        .await;
+        return if doctor::passed(&checks) { 0 } else { 1 };
+    }
+
+    let channel = match (notifier_kind, pushover_client) {
+        (notifier::NotifierKind::Pushover, Some(pushover_client)) => {
+            notifier::Channel::Pushover(pushover_client)
+        }
+        (notifier::NotifierKind::Stdout, _) => notifier::Channel::Stdout,
+        _ => notifier::Channel::Desktop,
+    };
+    let mut notifier = notifier::Notifier::from(channel);
+    if let Some(limit) = args.max_notifications_per_hour {
+        notifier = notifier.with_budget(limit);
+    }
+    let mut state = data::State::new();
+    let mut saved = None;
+    if let Some(path) = &args.state_file {
+        match state_file::StateFile::load(path) {
+            Ok(Some(mut saved)) if saved.matches(args.board(), args.title()) => {
+                if let Some(budget) = saved.budget.take() {
+                    notifier.restore_budget(budget);
+                }
+                state = resume(&args, &client, &notifier, saved).await;
+            }
+            Ok(Some(_)) => info!(
+                "Not resuming from {}, it was saved for another thread",
+                path.display()
+            ),
+            Ok(None) => {}
+            Err(error) => warn!("{}", error),
+        }
+        save_state(&args, &state, &notifier, &mut saved);
+        if let Some(code) = finished(&args, &state) {
+            println!("{}", summary(&args, &state, Utc::now()));
+            return code;
+        }
+    }
 
     let forever = task::spawn(async move {
         let mut interval = time::interval(Duration::from_secs(30));
-        let mut state = data::State::new();
-        let pushover_client: Option<pushover::PushoverClient> = match (
-            &args.pushover_application_api_token,
-            &args.pushover_user_key,
-        ) {
-            (Some(token), Some(user)) => Some(pushover::PushoverClient {
-                token: token.to_string(),
-                user: user.to_string(),
-            }),
-            _ => None,
-        };
 
         loop {
             interval.tick().await;
-            state = check(&args, &pushover_client, state).await;
+            if notifier.flush(args.label.as_ref()).await {
+                state.notifications_sent += 1;
+            }
+            state = check(&args, &client, &notifier, state).await;
+            save_state(&args, &state, &notifier, &mut saved);
+            if let Some(code) = finished(&args, &state) {
+                println!("{}", summary(&args, &state, Utc::now()));
+                return code;
+            }
         }
     });
 
-    let _ = forever.await;
+    if handle_signals {
+        #[cfg(unix)]
+        tokio::select! {
+            result = forever => return result.unwrap_or(1),
+            _ = daemon::shutdown_signal() => info!("Shutting down"),
+        }
+    } else {
+        return forever.await.unwrap_or(1);
+    }
+    0
 }
 
 #[cfg(test)]
@@ -140,6 +1053,9 @@ mod tests {
 
     use crate::pushover::PushoverClientTrait;
     use async_trait::async_trait;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
     use test_case::test_case;
 
     fn make_thread(page: i32) -> data::Thread {
@@ -150,13 +1066,20 @@ mod tests {
             time: chrono::offset::Utc::now(),
             position: 1,
             page_length: 10,
+            slot: (page - 1) * 10 + 1,
+            slot_count: 100,
+            stale: false,
         }
     }
 
-    #[derive(Clone, Copy)]
+    #[derive(Clone)]
     pub struct TestPushoverClient {
         disabled: bool,
         successful: bool,
+        expected_title: Option<&'static str>,
+        sent: Arc<AtomicUsize>,
+        low_priority_sent: Arc<AtomicUsize>,
+        messages: Arc<Mutex<Vec<String>>>,
     }
 
     impl TestPushoverClient {
@@ -164,6 +1087,10 @@ mod tests {
             return Self {
                 disabled: false,
                 successful: true,
+                expected_title: None,
+                sent: Arc::new(AtomicUsize::new(0)),
+                low_priority_sent: Arc::new(AtomicUsize::new(0)),
+                messages: Arc::new(Mutex::new(Vec::new())),
             };
         }
     }
@@ -172,15 +1099,51 @@ mod tests {
     impl PushoverClientTrait for TestPushoverClient {
         async fn send_notification(
             self: &Self,
-            _message: String,
-            _title: Option<&String>,
+            message: String,
+            title: Option<&String>,
         ) -> Result<(), ()> {
             assert!(!self.disabled);
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            self.messages.lock().unwrap().push(message);
+            if let Some(expected_title) = self.expected_title {
+                assert_eq!(title.map(|title| title.as_str()), Some(expected_title));
+            }
             return match self.successful {
                 true => Ok(()),
                 false => Err(()),
             };
         }
+
+        async fn send_low_priority_notification(
+            self: &Self,
+            message: String,
+            title: Option<&String>,
+        ) -> Result<(), ()> {
+            self.low_priority_sent.fetch_add(1, Ordering::SeqCst);
+            self.send_notification(message, title).await
+        }
+    }
+
+    fn pushover_notifier(
+        pushover_client: TestPushoverClient,
+    ) -> notifier::Notifier<TestPushoverClient> {
+        notifier::Notifier::from(notifier::Channel::Pushover(pushover_client))
+    }
+
+    /// Record the thread at the given slots, one sample every five minutes
+    /// starting `start` minutes into the fabricated history.
+    fn record_slide(state: &mut data::State, start: i64, slots: &[i32]) -> data::Thread {
+        let base = DateTime::from_timestamp(1700000000, 0).unwrap();
+        let mut thread = make_thread(1);
+        for (index, slot) in slots.iter().enumerate() {
+            thread = data::Thread {
+                time: base + chrono::Duration::minutes(start + 5 * index as i64),
+                slot: *slot,
+                ..make_thread(slot / 10 + 1)
+            };
+            state.history.record(&thread);
+        }
+        thread
     }
 
     #[test]
@@ -189,36 +1152,326 @@ mod tests {
         PagenineArgs::command().debug_assert()
     }
 
-    #[test_case("vg", "vg"; "plain")]
-    #[test_case("/vg/", "vg"; "with slashes")]
-    fn args_validate_board(input: &str, output: &str) {
-        assert_eq!(validate_board(&input), Ok(String::from(output)));
+    #[test_case(&[], None; "any family")]
+    #[test_case(&["--ipv4"], Some(client::IpFamily::V4); "ipv4")]
+    #[test_case(&["--ipv6"], Some(client::IpFamily::V6); "ipv6")]
+    fn args_ip_family(flags: &[&str], ip_family: Option<client::IpFamily>) {
+        let args =
+            PagenineArgs::try_parse_from([&["pagenine", "vg", "x"], flags].concat()).unwrap();
+        assert_eq!(args.ip_family(), ip_family);
     }
 
-    #[tokio::test]
-    async fn notify_exceed_threshold() {
-        let thread = make_thread(9);
-        let state = data::State {
-            thread: None,
-            notified: 0,
-        };
-        let pushover_client = TestPushoverClient::new();
-        let new_state = notify(state, thread.clone(), &Some(pushover_client)).await;
-        assert_eq!(new_state.thread, Some(thread));
-        assert_eq!(new_state.notified, 9);
+    #[test]
+    fn args_ip_family_conflict() {
+        let result = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--ipv4", "--ipv6"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
     }
 
-    #[tokio::test]
-    async fn notify_exceed_threshold_notification_failure() {
-        let thread = make_thread(9);
-        let state = data::State {
-            thread: None,
-            notified: 0,
-        };
-        let mut pushover_client = TestPushoverClient::new();
-        pushover_client.successful = false;
-        let new_state = notify(state, thread.clone(), &Some(pushover_client)).await;
-        assert_eq!(new_state.thread, Some(thread));
+    #[test_case("/jp/ - 𠮷野家 General 🍣\n", "/jp/ - 𠮷野家 General 🍣"; "trailing newline")]
+    #[test_case("𝔤𝔢𝔫𝔢𝔯𝔞𝔩\r\n", "𝔤𝔢𝔫𝔢𝔯𝔞𝔩"; "trailing crlf")]
+    #[test_case("🍣", "🍣"; "no trailing newline")]
+    #[test_case("🍣\n\n", "🍣\n"; "only one newline trimmed")]
+    #[test_case(" 🍣 \n", " 🍣 "; "whitespace kept")]
+    fn args_read_title(content: &str, title: &str) {
+        assert_eq!(read_title(content.as_bytes()), Ok(String::from(title)));
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("\n"; "only newline")]
+    fn args_read_title_empty(content: &str) {
+        assert!(read_title(content.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn args_read_title_invalid_utf8() {
+        assert!(read_title(&[0xf0, 0x9f, 0x8d][..]).is_err());
+    }
+
+    #[test]
+    fn args_title_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all("/jp/ - 𠮷野家 General 🍣\n".as_bytes())
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+        let mut args =
+            PagenineArgs::try_parse_from(["pagenine", "jp", "--title-file", path]).unwrap();
+        args.resolve_title().unwrap();
+        assert_eq!(args.title(), "/jp/ - 𠮷野家 General 🍣");
+    }
+
+    #[test]
+    fn args_title_file_missing() {
+        let mut args =
+            PagenineArgs::try_parse_from(["pagenine", "jp", "--title-file", "/nonexistent/title"])
+                .unwrap();
+        assert!(args.resolve_title().is_err());
+    }
+
+    #[test]
+    fn args_title_positional() {
+        let mut args = PagenineArgs::try_parse_from(["pagenine", "jp", "🍣"]).unwrap();
+        args.resolve_title().unwrap();
+        assert_eq!(args.title(), "🍣");
+    }
+
+    #[test]
+    fn args_title_conflict() {
+        let result =
+            PagenineArgs::try_parse_from(["pagenine", "jp", "x", "--title-file", "title.txt"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn args_doctor() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "doctor", "/vg/", "xyz"]).unwrap();
+        let Some(Command::Doctor { board, title }) = args.command else {
+            panic!("expected doctor subcommand");
+        };
+        assert_eq!(board, Some(String::from("vg")));
+        assert_eq!(title, Some(String::from("xyz")));
+        assert!(args.board.is_none());
+    }
+
+    #[test]
+    fn args_doctor_without_arguments() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "doctor"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Doctor {
+                board: None,
+                title: None
+            })
+        ));
+    }
+
+    #[test]
+    fn args_doctor_with_pushover() {
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "doctor",
+            "--pushover-application-api-token",
+            "token",
+            "--pushover-user-key",
+            "user",
+        ])
+        .unwrap();
+        let pushover_client = make_pushover_client(&args, &reqwest::Client::new()).unwrap();
+        assert_eq!(pushover_client.token, "token");
+        assert_eq!(pushover_client.users, vec![String::from("user")]);
+    }
+
+    #[test]
+    fn args_board_required() {
+        let args = PagenineArgs::try_parse_from(["pagenine"]).unwrap();
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn args_title_required() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "jp"]).unwrap();
+        assert!(args.validate().is_err());
+    }
+
+    fn make_config() -> config::Config {
+        config::Config {
+            board: Some(String::from("/jp/")),
+            title: Some(String::from("config title")),
+            label: Some(String::from("JP")),
+            pushover_application_api_token: Some(String::from("token")),
+            pushover_user_key: Some(String::from("user")),
+            notifier: None,
+            state_file: None,
+            #[cfg(unix)]
+            log_file: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn args_daemon() {
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "x",
+            "--daemon",
+            "--pid-file",
+            "/run/user/1000/pagenine.pid",
+            "--log-file",
+            "pagenine.log",
+        ])
+        .unwrap();
+        assert!(args.daemon);
+        assert_eq!(
+            args.pid_file,
+            Some(PathBuf::from("/run/user/1000/pagenine.pid"))
+        );
+        assert_eq!(args.log_file, Some(PathBuf::from("pagenine.log")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn args_log_file_requires_daemon() {
+        let result = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--log-file", "a.log"]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn start_daemon_pid_file_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        std::fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "x",
+            "--pid-file",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert!(start_daemon(&args).is_err());
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn start_daemon_foreground_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pagenine.pid");
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "x",
+            "--pid-file",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let pid_file = start_daemon(&args).unwrap();
+        assert!(pid_file.is_some());
+        assert!(path.exists());
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test_case(&["--notifier", "stdout"], true; "stdout")]
+    #[test_case(&["--notifier", "pushover"], false; "pushover without credentials")]
+    #[test_case(
+        &["--notifier", "stdout", "--pushover-application-api-token", "t", "--pushover-user-key", "u"],
+        true;
+        "stdout over pushover"
+    )]
+    #[tokio::test]
+    async fn preflight_notifier(flags: &[&str], passed: bool) {
+        let args =
+            PagenineArgs::try_parse_from([&["pagenine", "vg", "x"], flags].concat()).unwrap();
+        assert_eq!(preflight(&args).await.is_ok(), passed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn start_daemon_without_flags() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        assert!(start_daemon(&args).unwrap().is_none());
+    }
+
+    #[test]
+    fn args_apply_config() {
+        let mut args = PagenineArgs::try_parse_from(["pagenine"]).unwrap();
+        args.apply_config(make_config());
+        assert!(args.validate().is_ok());
+        assert_eq!(args.board(), "jp");
+        assert_eq!(args.title, Some(String::from("config title")));
+        assert_eq!(args.label, Some(String::from("JP")));
+        assert_eq!(
+            args.pushover_application_api_token,
+            Some(String::from("token"))
+        );
+        assert_eq!(args.pushover_user_key, vec![String::from("user")]);
+    }
+
+    #[test]
+    fn args_apply_config_command_line_precedence() {
+        let mut args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "cli title",
+            "--label",
+            "VG",
+            "--pushover-user-key",
+            "other",
+        ])
+        .unwrap();
+        args.apply_config(make_config());
+        assert_eq!(args.board(), "vg");
+        assert_eq!(args.title, Some(String::from("cli title")));
+        assert_eq!(args.label, Some(String::from("VG")));
+        assert_eq!(
+            args.pushover_application_api_token,
+            Some(String::from("token"))
+        );
+        assert_eq!(args.pushover_user_key, vec![String::from("other")]);
+    }
+
+    #[test]
+    fn args_apply_config_title_file_precedence() {
+        let mut args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "--title-file", "title.txt"]).unwrap();
+        args.apply_config(make_config());
+        assert!(args.title.is_none());
+        assert!(args.validate().is_ok());
+    }
+
+    #[test_case("vg", "vg"; "plain")]
+    #[test_case("/vg/", "vg"; "with slashes")]
+    fn args_validate_board(input: &str, output: &str) {
+        assert_eq!(validate_board(&input), Ok(String::from(output)));
+    }
+
+    #[tokio::test]
+    async fn notify_exceed_threshold() {
+        let thread = make_thread(9);
+        let state = data::State {
+            notified: 0,
+            ..data::State::new()
+        };
+        let pushover_client = TestPushoverClient::new();
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
+        assert_eq!(new_state.thread, Some(thread));
+        assert_eq!(new_state.notified, 9);
+    }
+
+    #[tokio::test]
+    async fn notify_exceed_threshold_notification_failure() {
+        let thread = make_thread(9);
+        let state = data::State {
+            notified: 0,
+            ..data::State::new()
+        };
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.successful = false;
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
+        assert_eq!(new_state.thread, Some(thread));
         assert_eq!(new_state.notified, 0);
     }
 
@@ -226,12 +1479,18 @@ mod tests {
     async fn notify_over_threshold_already_notified() {
         let thread = make_thread(9);
         let state = data::State {
-            thread: None,
             notified: 9,
+            ..data::State::new()
         };
         let mut pushover_client = TestPushoverClient::new();
         pushover_client.disabled = true;
-        let new_state = notify(state, thread.clone(), &Some(pushover_client)).await;
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
         assert_eq!(new_state.thread, Some(thread));
         assert_eq!(new_state.notified, 9);
     }
@@ -240,25 +1499,957 @@ mod tests {
     async fn notify_over_threshold_page_after() {
         let thread = make_thread(10);
         let state = data::State {
-            thread: None,
             notified: 9,
+            ..data::State::new()
         };
         let pushover_client = TestPushoverClient::new();
-        let new_state = notify(state, thread.clone(), &Some(pushover_client)).await;
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
         assert_eq!(new_state.thread, Some(thread));
         assert_eq!(new_state.notified, 10);
     }
 
+    #[tokio::test]
+    async fn notify_with_label() {
+        let thread = make_thread(9);
+        let state = data::State {
+            notified: 0,
+            ..data::State::new()
+        };
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.expected_title = Some("XYZ");
+        let label = String::from("XYZ");
+        let new_state = notify(
+            state,
+            thread.clone(),
+            Some(&label),
+            &pushover_notifier(pushover_client),
+        )
+        .await;
+        assert_eq!(new_state.notified, 9);
+    }
+
+    #[tokio::test]
+    async fn notify_without_label() {
+        let thread = make_thread(9);
+        let state = data::State {
+            notified: 0,
+            ..data::State::new()
+        };
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.expected_title = Some("x");
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
+        assert_eq!(new_state.notified, 9);
+    }
+
+    #[test_case(&[], Ok(notifier::NotifierKind::Desktop); "default desktop")]
+    #[test_case(
+        &["--pushover-application-api-token", "t", "--pushover-user-key", "u"],
+        Ok(notifier::NotifierKind::Pushover);
+        "default pushover"
+    )]
+    #[test_case(
+        &["--pushover-application-api-token", "t", "--pushover-user-key", "u", "--notifier", "stdout"],
+        Ok(notifier::NotifierKind::Stdout);
+        "explicit over pushover"
+    )]
+    #[test_case(&["--notifier", "stdout"], Ok(notifier::NotifierKind::Stdout); "stdout")]
+    #[test_case(&["--notifier", "desktop"], Ok(notifier::NotifierKind::Desktop); "desktop")]
+    #[test_case(&["--notifier", "pushover"], Err(()); "pushover without credentials")]
+    fn args_notifier_kind(flags: &[&str], kind: Result<notifier::NotifierKind, ()>) {
+        let args =
+            PagenineArgs::try_parse_from([&["pagenine", "vg", "x"], flags].concat()).unwrap();
+        assert_eq!(args.notifier_kind().map_err(|_| ()), kind);
+    }
+
+    #[tokio::test]
+    async fn notify_start_once() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--notify-start"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let low_priority_sent = pushover_client.low_priority_sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let thread = make_thread(4);
+
+        let mut state = data::State::new();
+        for _ in 0..3 {
+            state = notify_start(state, Some(&thread), &args, &notifier).await;
+        }
+        assert!(state.start_notified);
+        assert_eq!(state.notified, 0);
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 1);
+
+        state = state.reset();
+        state = notify_start(state, None, &args, &notifier).await;
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 1);
+
+        let state = notify(state, make_thread(9), None, &notifier).await;
+        assert_eq!(state.notified, 9);
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn notify_start_no_match() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--label", "XYZ"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.expected_title = Some("XYZ");
+        let low_priority_sent = pushover_client.low_priority_sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let state = notify_start(data::State::new(), None, &args, &notifier).await;
+        assert!(state.start_notified);
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn notify_start_failure_retried() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.successful = false;
+        let low_priority_sent = pushover_client.low_priority_sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let state = notify_start(data::State::new(), None, &args, &notifier).await;
+        assert!(!state.start_notified);
+        let state = notify_start(state, None, &args, &notifier).await;
+        assert!(!state.start_notified);
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn effective_board_redirected() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "old", "x"]).unwrap();
+        let mut state = data::State::new();
+        assert_eq!(effective_board(&args, &state), "old");
+        state.board = Some(String::from("new"));
+        assert_eq!(effective_board(&args, &state.reset()), "new");
+    }
+
+    #[tokio::test]
+    async fn notify_stdout() {
+        let thread = make_thread(9);
+        let state = data::State {
+            notified: 0,
+            ..data::State::new()
+        };
+        let notifier: notifier::Notifier<TestPushoverClient> =
+            notifier::Notifier::from(notifier::Channel::Stdout);
+        let new_state = notify(state, thread.clone(), None, &notifier).await;
+        assert_eq!(new_state.notified, 9);
+    }
+
+    #[test_case(&[], ""; "without label")]
+    #[test_case(&["--label", "XYZ"], "[XYZ] "; "with label")]
+    fn args_log_prefix(flags: &[&str], prefix: &str) {
+        let args =
+            PagenineArgs::try_parse_from([&["pagenine", "vg", "x"], flags].concat()).unwrap();
+        assert_eq!(args.log_prefix(), prefix);
+    }
+
     #[tokio::test]
     async fn notify_reset_notified() {
         let thread = make_thread(1);
         let state = data::State {
-            thread: None,
             notified: 9,
+            ..data::State::new()
         };
         let pushover_client = TestPushoverClient::new();
-        let new_state = notify(state, thread.clone(), &Some(pushover_client)).await;
+        let new_state = notify(
+            state,
+            thread.clone(),
+            None,
+            &pushover_notifier(pushover_client),
+        )
+        .await;
         assert_eq!(new_state.thread, Some(thread));
         assert_eq!(new_state.notified, 0);
     }
+
+    #[tokio::test]
+    async fn notify_eta_fast_slide() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--eta-threshold", "30"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let sent = pushover_client.sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+
+        // Two slots per minute with 40 slots left is 20 minutes, even on page 6.
+        let mut state = data::State::new();
+        let thread = record_slide(&mut state, 0, &[40, 50, 60]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(state.eta_notified);
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+        let thread = record_slide(&mut state, 15, &[70]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(state.notified, 0);
+    }
+
+    #[tokio::test]
+    async fn notify_eta_slow_slide() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--eta-threshold", "30"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+
+        // One slot every five minutes with 10 slots left is 50 minutes.
+        let mut state = data::State::new();
+        let thread = record_slide(&mut state, 0, &[87, 88, 89, 90]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(!state.eta_notified);
+    }
+
+    #[tokio::test]
+    async fn notify_eta_too_little_history() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--eta-threshold", "30"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+
+        let mut state = data::State::new();
+        let thread = record_slide(&mut state, 0, &[90, 99]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(!state.eta_notified);
+    }
+
+    #[tokio::test]
+    async fn notify_eta_rearm_after_bump() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--eta-threshold", "30"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let sent = pushover_client.sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+
+        let mut state = data::State::new();
+        let thread = record_slide(&mut state, 0, &[40, 50, 60]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+        // Bumped to the front and sliding at the same pace is 39 minutes.
+        let thread = record_slide(&mut state, 15, &[2, 12, 22]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(!state.eta_notified);
+
+        let thread = record_slide(&mut state, 30, &[32, 42, 52, 62, 72]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(state.eta_notified);
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn notify_eta_disabled() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+
+        let mut state = data::State::new();
+        let thread = record_slide(&mut state, 0, &[80, 90, 99]);
+        state = notify_eta(state, &thread, &args, &notifier).await;
+        assert!(!state.eta_notified);
+    }
+
+    #[test_case("0"; "zero")]
+    #[test_case("-5"; "negative")]
+    fn args_eta_threshold_invalid(value: &str) {
+        let result =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--eta-threshold", value]);
+        assert!(result.is_err());
+    }
+
+    #[test_case("0"; "zero")]
+    #[test_case("-5"; "negative")]
+    fn args_stale_threshold_invalid(value: &str) {
+        let result =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--stale-threshold", value]);
+        assert!(result.is_err());
+    }
+
+    #[test_case(&["--pushover-user-key", "alice,bobby"]; "comma separated")]
+    #[test_case(&["--pushover-user-key", "alice", "--pushover-user-key", "bobby"]; "repeated")]
+    #[test_case(&["--pushover-user-key", "alice, ", "--pushover-user-key", " bobby"]; "whitespace")]
+    fn args_multiple_pushover_user_keys(flags: &[&str]) {
+        let args = PagenineArgs::try_parse_from(
+            [
+                &[
+                    "pagenine",
+                    "vg",
+                    "x",
+                    "--pushover-application-api-token",
+                    "token",
+                ],
+                flags,
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let pushover_client = make_pushover_client(&args, &reqwest::Client::new()).unwrap();
+        assert_eq!(
+            pushover_client.users,
+            vec![String::from("alice"), String::from("bobby")]
+        );
+    }
+
+    #[test]
+    fn args_apply_config_multiple_pushover_user_keys() {
+        let mut args = PagenineArgs::try_parse_from(["pagenine"]).unwrap();
+        args.apply_config(config::Config {
+            pushover_user_key: Some(String::from("alice,bobby")),
+            ..make_config()
+        });
+        assert_eq!(
+            args.pushover_user_keys(),
+            vec![String::from("alice"), String::from("bobby")]
+        );
+    }
+
+    #[tokio::test]
+    async fn check_death_still_alive() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+        let thread = make_thread(10);
+        let state = data::State {
+            thread: Some(thread.clone()),
+            missing: true,
+            ..data::State::new()
+        };
+        let state = check_death(state, false, &args, &notifier).await.unwrap();
+        assert!(!state.missing);
+        assert_eq!(state.thread, Some(thread.clone()));
+    }
+
+    #[tokio::test]
+    async fn check_death_confirmed() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--follow"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.expected_title = Some(">dead");
+        let sent = pushover_client.sent.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let thread = make_thread(10);
+        let state = data::State {
+            thread: Some(thread.clone()),
+            notified: 10,
+            eta_notified: true,
+            ..data::State::new()
+        };
+
+        let state = check_death(state, true, &args, &notifier)
+            .await
+            .unwrap_err();
+        assert!(state.missing);
+        assert_eq!(state.thread, Some(thread.clone()));
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+        let state = check_death(state, true, &args, &notifier).await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(state.died, Some(thread.clone()));
+        assert_eq!(state.notifications_sent, 1);
+        assert!(state.thread.is_none());
+        assert!(!state.missing);
+        assert_eq!(state.notified, 0);
+        assert!(!state.eta_notified);
+    }
+
+    #[tokio::test]
+    async fn check_death_exit() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--exit-on-death"]).unwrap();
+        let notifier = pushover_notifier(TestPushoverClient::new());
+        let thread = make_thread(10);
+        let state = data::State {
+            thread: Some(thread.clone()),
+            missing: true,
+            ..data::State::new()
+        };
+        let state = check_death(state, true, &args, &notifier)
+            .await
+            .unwrap_err();
+        assert_eq!(state.died, Some(thread.clone()));
+    }
+
+    #[tokio::test]
+    async fn check_death_default_follow() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let notifier = pushover_notifier(TestPushoverClient::new());
+        let state = data::State {
+            thread: Some(make_thread(10)),
+            missing: true,
+            ..data::State::new()
+        };
+        let state = check_death(state, true, &args, &notifier).await.unwrap();
+        assert!(state.died.is_some());
+        assert!(state.thread.is_none());
+    }
+
+    #[tokio::test]
+    async fn check_death_nothing_watched() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--exit-on-death"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+        let state = check_death(data::State::new(), false, &args, &notifier)
+            .await
+            .unwrap();
+        assert!(state.died.is_none());
+    }
+
+    #[test_case(&[], None, None; "alive")]
+    #[test_case(&[], Some(10), None; "died")]
+    #[test_case(&["--exit-on-death"], Some(10), Some(EXIT_THREAD_DIED); "died with exit on death")]
+    #[test_case(&["--follow"], Some(10), None; "died with follow")]
+    fn finished_after_death(flags: &[&str], died: Option<i32>, code: Option<i32>) {
+        let args =
+            PagenineArgs::try_parse_from([&["pagenine", "vg", "x"], flags].concat()).unwrap();
+        let state = data::State {
+            died: died.map(make_thread),
+            ..data::State::new()
+        };
+        assert_eq!(finished(&args, &state), code);
+    }
+
+    #[test]
+    fn args_exit_on_death_follow_conflict() {
+        let result =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--follow", "--exit-on-death"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn find_thread_by_number() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let catalog: api::Catalog = serde_json::from_str(
+            r#"[
+                {"page": 1, "threads": [{"no": 200, "sub": "/xyz/ #2"}]},
+                {"page": 9, "threads": [{"no": 100, "sub": "Renamed"}]}
+            ]"#,
+        )
+        .unwrap();
+
+        let lookup = find_thread(&args, &catalog, None);
+        assert_eq!(lookup.thread.map(|thread| thread.no), Some(200));
+        assert!(!lookup.watched_missing);
+
+        let lookup = find_thread(&args, &catalog, Some(100));
+        assert_eq!(lookup.thread.map(|thread| thread.no), Some(100));
+        assert!(!lookup.watched_missing);
+
+        let lookup = find_thread(&args, &catalog, Some(300));
+        assert_eq!(lookup.thread.map(|thread| thread.no), Some(200));
+        assert!(lookup.watched_missing);
+    }
+
+    #[test]
+    fn summary_after_death() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let started = DateTime::from_timestamp(1700000000, 0).unwrap();
+        let state = data::State {
+            died: Some(make_thread(10)),
+            started,
+            notifications_sent: 2,
+            ..data::State::new()
+        };
+        assert_eq!(
+            summary(&args, &state, started + chrono::Duration::minutes(135)),
+            "\"x\" (No. 123456) on /vg/ died after page 10 (1/10)\nWatched for 2h 15min, 2 notifications sent"
+        );
+    }
+
+    #[test]
+    fn summary_without_death() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x"]).unwrap();
+        let state = data::State::new();
+        assert_eq!(
+            summary(&args, &state, state.started),
+            "Watched for 0h 0min, 0 notifications sent"
+        );
+    }
+
+    const SAVED_STATE: &str = r#"{
+        "board": "vg",
+        "title": "/xyz/",
+        "redirected_board": null,
+        "thread": {
+            "page": 9,
+            "no": 100,
+            "sub": "/xyz/ #1",
+            "time": "2023-11-14T22:00:00Z",
+            "position": 3,
+            "page_length": 15,
+            "slot": 123,
+            "slot_count": 150,
+            "stale": false
+        },
+        "notified": 9,
+        "eta_notified": false
+    }"#;
+
+    /// Reconcile a state file with the given catalog and what the API said
+    /// about the saved thread.
+    async fn reconcile_saved(
+        args: &PagenineArgs,
+        content: &str,
+        catalog: &str,
+        status: api::ThreadStatus,
+        notifier: &notifier::Notifier<TestPushoverClient>,
+    ) -> data::State {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, content).unwrap();
+        let saved = state_file::StateFile::load(&path).unwrap().unwrap();
+        assert!(saved.matches(args.board(), args.title()));
+        let catalog: api::Catalog = serde_json::from_str(catalog).unwrap();
+        let offline = reconcile_thread(args, saved.thread.clone().unwrap(), &catalog, status);
+        report_offline(saved.to_state(), offline, args, notifier).await
+    }
+
+    #[tokio::test]
+    async fn resume_thread_alive() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+        let state = reconcile_saved(
+            &args,
+            SAVED_STATE,
+            r#"[{"page": 10, "threads": [{"no": 99, "sub": "Other"}, {"no": 100, "sub": "/xyz/ #1"}]}]"#,
+            api::ThreadStatus::Open,
+            &notifier,
+        )
+        .await;
+        let thread = state.thread.unwrap();
+        assert_eq!((thread.no, thread.page, thread.position), (100, 10, 2));
+        assert_eq!(state.notified, 9);
+        assert!(state.died.is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_thread_missing_from_catalog_but_open() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+        let state = reconcile_saved(
+            &args,
+            SAVED_STATE,
+            r#"[{"page": 1, "threads": [{"no": 101, "sub": "Other"}]}]"#,
+            api::ThreadStatus::Open,
+            &notifier,
+        )
+        .await;
+        assert_eq!(state.thread.unwrap().no, 100);
+        assert!(state.died.is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_thread_archived() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let messages = pushover_client.messages.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let state = reconcile_saved(
+            &args,
+            SAVED_STATE,
+            r#"[{"page": 1, "threads": [{"no": 101, "sub": "Other"}]}]"#,
+            api::ThreadStatus::Archived(DateTime::from_timestamp(1700000000, 0).unwrap()),
+            &notifier,
+        )
+        .await;
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![String::from(
+                "Thread died while pagenine was off (archived at 2023-11-14 22:13 UTC)\n/xyz/ #1"
+            )]
+        );
+        assert!(state.thread.is_none());
+        assert_eq!(state.died.unwrap().no, 100);
+        assert_eq!(state.notified, 0);
+        assert_eq!(state.notifications_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn resume_thread_replaced() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/", "--follow"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let messages = pushover_client.messages.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let state = reconcile_saved(
+            &args,
+            SAVED_STATE,
+            r#"[{"page": 1, "threads": [{"no": 200, "sub": "/xyz/ #2"}]}]"#,
+            api::ThreadStatus::Gone,
+            &notifier,
+        )
+        .await;
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![String::from(
+                "Thread died while pagenine was off\n/xyz/ #1\nNow watching: /xyz/ #2 on /vg/, currently page 1 (1/1)"
+            )]
+        );
+        assert_eq!(state.died.unwrap().no, 100);
+        assert!(state.thread.is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_thread_replaced_exit_on_death() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/", "--exit-on-death"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let messages = pushover_client.messages.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let state = reconcile_saved(
+            &args,
+            SAVED_STATE,
+            r#"[{"page": 1, "threads": [{"no": 200, "sub": "/xyz/ #2"}]}]"#,
+            api::ThreadStatus::Gone,
+            &notifier,
+        )
+        .await;
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![String::from(
+                "Thread died while pagenine was off\n/xyz/ #1\nNewer thread: /xyz/ #2 on /vg/, currently page 1 (1/1)"
+            )]
+        );
+        assert_eq!(finished(&args, &state), Some(EXIT_THREAD_DIED));
+    }
+
+    async fn resume_with_server(
+        args: &PagenineArgs,
+        server: &mockito::Server,
+        notifier: &notifier::Notifier<TestPushoverClient>,
+    ) -> data::State {
+        let state_file = serde_json::from_str(SAVED_STATE).unwrap();
+        resume_from(
+            args,
+            &reqwest::Client::new(),
+            &server.url(),
+            notifier,
+            state_file,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn resume_alive() {
+        let args =
+            PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/", "--notify-start"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let low_priority_sent = pushover_client.low_priority_sent.clone();
+        let messages = pushover_client.messages.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 10, "threads": [{"no": 100, "sub": "/xyz/ #1"}]}]"#)
+            .create_async()
+            .await;
+        let thread_status = server
+            .mock("GET", "/vg/thread/100.json")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let state = resume_with_server(&args, &server, &notifier).await;
+        assert_eq!(state.thread.unwrap().page, 10);
+        assert_eq!(state.notified, 10);
+        assert!(state.start_notified);
+        assert_eq!(state.notifications_sent, 2);
+        assert_eq!(low_priority_sent.load(Ordering::SeqCst), 1);
+        thread_status.assert_async().await;
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("Watching: /xyz/ #1"));
+    }
+
+    #[tokio::test]
+    async fn resume_archived() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let messages = pushover_client.messages.clone();
+        let notifier = pushover_notifier(pushover_client);
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"page": 1, "threads": [{"no": 101, "sub": "Other"}]}]"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/vg/thread/100.json")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"posts": [{"no": 100, "archived": 1, "archived_on": 1700000000}]}"#)
+            .create_async()
+            .await;
+
+        let state = resume_with_server(&args, &server, &notifier).await;
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![String::from(
+                "Thread died while pagenine was off (archived at 2023-11-14 22:13 UTC)\n/xyz/ #1"
+            )]
+        );
+        assert_eq!(state.died.unwrap().no, 100);
+        assert!(state.thread.is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_fetch_failed() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "/xyz/"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.disabled = true;
+        let notifier = pushover_notifier(pushover_client);
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/vg/catalog.json")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let state = resume_with_server(&args, &server, &notifier).await;
+        assert_eq!(state.thread.unwrap().no, 100);
+        assert!(state.died.is_none());
+    }
+
+    #[test]
+    fn resume_other_thread() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, SAVED_STATE).unwrap();
+        let saved = state_file::StateFile::load(&path).unwrap().unwrap();
+        assert!(!saved.matches(&String::from("vg"), &String::from("/abc/")));
+        assert!(!saved.matches(&String::from("jp"), &String::from("/xyz/")));
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "/xyz/",
+            "--state-file",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let thread = make_thread(9);
+        let state = data::State {
+            thread: Some(thread.clone()),
+            notified: 9,
+            ..data::State::new()
+        };
+        let notifier: notifier::Notifier<TestPushoverClient> =
+            notifier::Notifier::from(notifier::Channel::Stdout);
+        save_state(&args, &state, &notifier, &mut None);
+        let saved = state_file::StateFile::load(&path).unwrap().unwrap();
+        assert!(saved.matches(args.board(), args.title()));
+        assert_eq!(saved.thread, Some(thread));
+        assert_eq!(saved.to_state().notified, 9);
+        assert!(saved.budget.is_none());
+    }
+
+    #[test]
+    fn save_state_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "/xyz/",
+            "--state-file",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let notifier: notifier::Notifier<TestPushoverClient> =
+            notifier::Notifier::from(notifier::Channel::Stdout);
+        let mut state = data::State {
+            thread: Some(make_thread(8)),
+            ..data::State::new()
+        };
+        let mut saved = None;
+        save_state(&args, &state, &notifier, &mut saved);
+        assert!(path.exists());
+
+        std::fs::remove_file(&path).unwrap();
+        if let Some(thread) = &mut state.thread {
+            thread.time = Utc::now() + chrono::Duration::minutes(1);
+        }
+        save_state(&args, &state, &notifier, &mut saved);
+        assert!(!path.exists());
+
+        state.notified = 9;
+        save_state(&args, &state, &notifier, &mut saved);
+        assert_eq!(
+            state_file::StateFile::load(&path)
+                .unwrap()
+                .unwrap()
+                .notified,
+            9
+        );
+    }
+
+    fn make_budget_notifier(
+        pushover_client: TestPushoverClient,
+        limit: u32,
+    ) -> notifier::Notifier<TestPushoverClient> {
+        pushover_notifier(pushover_client).with_budget(limit)
+    }
+
+    #[tokio::test]
+    async fn notify_budget_exhausted() {
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "x",
+            "--max-notifications-per-hour",
+            "1",
+        ])
+        .unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let sent = pushover_client.sent.clone();
+        let notifier = make_budget_notifier(pushover_client, 1);
+
+        let state = notify_start(data::State::new(), Some(&make_thread(4)), &args, &notifier).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+        // Held back notifications count as handled so they are not retried,
+        // but not as sent.
+        let state = notify(state, make_thread(9), None, &notifier).await;
+        assert_eq!(state.notified, 9);
+        let state = notify(state, make_thread(10), None, &notifier).await;
+        assert_eq!(state.notified, 10);
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(state.notifications_sent, 1);
+
+        // Not yet an hour, so no digest.
+        assert!(!notifier.flush(None).await);
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn notify_budget_critical_exempt() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--follow"]).unwrap();
+        let pushover_client = TestPushoverClient::new();
+        let sent = pushover_client.sent.clone();
+        let notifier = make_budget_notifier(pushover_client, 1);
+
+        let state = notify(data::State::new(), make_thread(9), None, &notifier).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+        let final_slot = data::Thread {
+            slot: 100,
+            ..make_thread(10)
+        };
+        let state = notify(state, final_slot.clone(), None, &notifier).await;
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+        let state = data::State {
+            thread: Some(final_slot),
+            missing: true,
+            ..state
+        };
+        let state = check_death(state, true, &args, &notifier).await.unwrap();
+        assert!(state.died.is_some());
+        assert_eq!(sent.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn notify_budget_digest() {
+        let args = PagenineArgs::try_parse_from(["pagenine", "vg", "x", "--label", "XYZ"]).unwrap();
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.expected_title = Some("XYZ");
+        let messages = pushover_client.messages.clone();
+        let notifier = make_budget_notifier(pushover_client, 1);
+
+        // Pretend the budget was used up over an hour ago by an earlier run.
+        let mut saved = budget::Budget::new(1);
+        let earlier = Utc::now() - chrono::Duration::minutes(61);
+        assert!(saved.admit(earlier, String::from("XYZ: >page 9 - x")));
+        assert!(!saved.admit(earlier, String::from("XYZ: >page 10 - x")));
+        notifier.restore_budget(saved);
+
+        // New notifications wait until the digest is out.
+        let state = notify(
+            data::State::new(),
+            make_thread(9),
+            Some(&String::from("XYZ")),
+            &notifier,
+        )
+        .await;
+        assert_eq!(state.notified, 9);
+        assert!(messages.lock().unwrap().is_empty());
+
+        assert!(notifier.flush(args.label.as_ref()).await);
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![String::from(
+                "2 notifications held back in the last hour:\n- XYZ: >page 10 - x\n- XYZ: >page 9 - x"
+            )]
+        );
+        assert!(!notifier.flush(args.label.as_ref()).await);
+        assert_eq!(messages.lock().unwrap().len(), 1);
+
+        // The digest used up the new window.
+        let _ = notify_start(state, None, &args, &notifier).await;
+        assert_eq!(messages.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn notify_budget_digest_failed() {
+        let mut pushover_client = TestPushoverClient::new();
+        pushover_client.successful = false;
+        let sent = pushover_client.sent.clone();
+        let notifier = make_budget_notifier(pushover_client, 1);
+
+        let mut saved = budget::Budget::new(1);
+        let earlier = Utc::now() - chrono::Duration::minutes(61);
+        assert!(saved.admit(earlier, String::from("first")));
+        assert!(!saved.admit(earlier, String::from("second")));
+        notifier.restore_budget(saved.clone());
+
+        assert!(!notifier.flush(None).await);
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(notifier.budget(), Some(saved));
+    }
+
+    #[test]
+    fn save_state_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let args = PagenineArgs::try_parse_from([
+            "pagenine",
+            "vg",
+            "x",
+            "--state-file",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let notifier = make_budget_notifier(TestPushoverClient::new(), 1);
+        let mut budget = budget::Budget::new(1);
+        assert!(budget.admit(Utc::now(), String::from("first")));
+        assert!(!budget.admit(Utc::now(), String::from("second")));
+        notifier.restore_budget(budget);
+        save_state(&args, &data::State::new(), &notifier, &mut None);
+
+        let saved = state_file::StateFile::load(&path).unwrap().unwrap();
+        let restored = make_budget_notifier(TestPushoverClient::new(), 1);
+        restored.restore_budget(saved.budget.unwrap());
+        assert_eq!(restored.budget(), notifier.budget());
+    }
 }
@@ -0,0 +1,331 @@
+use crate::budget::Budget;
+use crate::data;
+use crate::pushover::PushoverClientTrait;
+use chrono::prelude::Utc;
+use log::{info, warn};
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::sync::Mutex;
+
+/// Notifier selectable on the command line.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    /// Operating system notifications.
+    Desktop,
+    /// Pushover push notifications.
+    Pushover,
+    /// Lines printed to standard output.
+    Stdout,
+}
+
+/// Destination for notifications about the thread.
+#[derive(Debug)]
+pub enum Channel<P: PushoverClientTrait> {
+    Desktop,
+    Pushover(P),
+    Stdout,
+}
+
+/// How important a notification is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Normal,
+    /// Always delivered, regardless of the notification budget.
+    Critical,
+}
+
+/// What happened to a notification that was handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// Held back for the digest since the budget was exhausted.
+    Held,
+}
+
+impl Delivery {
+    /// Number of notifications that were actually sent.
+    pub fn count(&self) -> i32 {
+        match self {
+            Delivery::Sent => 1,
+            Delivery::Held => 0,
+        }
+    }
+}
+
+/// Delivers notifications to the channel within the notification budget.
+#[derive(Debug)]
+pub struct Notifier<P: PushoverClientTrait> {
+    pub channel: Channel<P>,
+    budget: Option<Mutex<Budget>>,
+}
+
+impl<P: PushoverClientTrait> From<Channel<P>> for Notifier<P> {
+    fn from(channel: Channel<P>) -> Self {
+        Notifier {
+            channel,
+            budget: None,
+        }
+    }
+}
+
+impl<P: PushoverClientTrait> Notifier<P> {
+    /// Limit the number of non-critical notifications delivered per hour.
+    pub fn with_budget(self, limit: u32) -> Self {
+        Notifier {
+            budget: Some(Mutex::new(Budget::new(limit))),
+            ..self
+        }
+    }
+
+    /// Get the current budget for saving it.
+    pub fn budget(&self) -> Option<Budget> {
+        self.budget
+            .as_ref()
+            .map(|budget| budget.lock().unwrap().clone())
+    }
+
+    /// Continue with a budget saved by an earlier run.
+    pub fn restore_budget(&self, saved: Budget) {
+        if let Some(budget) = &self.budget {
+            budget.lock().unwrap().restore(saved);
+        }
+    }
+
+    /// Send a notification about the thread.
+    ///
+    /// Notifications about the thread being in the final slot are critical.
+    pub async fn send(
+        &self,
+        thread: &data::Thread,
+        label: Option<&String>,
+    ) -> Result<Delivery, ()> {
+        let severity = match thread.is_in_final_slot() {
+            true => Severity::Critical,
+            false => Severity::Normal,
+        };
+        let (summary, body) = thread.notification_text(label);
+        if !self.admit(severity, &summary, &body) {
+            return Ok(Delivery::Held);
+        }
+        let result = match &self.channel {
+            Channel::Desktop => thread.show_notification(label),
+            Channel::Pushover(pushover_client) => {
+                thread
+                    .send_pushover_notification(pushover_client, label)
+                    .await
+            }
+            Channel::Stdout => {
+                thread.print_notification(label);
+                Ok(())
+            }
+        };
+        self.settle(severity, result)
+    }
+
+    /// Send a normal priority notification that is not about a page change.
+    pub async fn send_alert(
+        &self,
+        title: &String,
+        message: String,
+        severity: Severity,
+    ) -> Result<Delivery, ()> {
+        if !self.admit(severity, title, &message) {
+            return Ok(Delivery::Held);
+        }
+        let result = self.deliver(title, message).await;
+        self.settle(severity, result)
+    }
+
+    /// Send a low priority informational notification.
+    pub async fn send_status(&self, title: &String, message: String) -> Result<Delivery, ()> {
+        if !self.admit(Severity::Normal, title, &message) {
+            return Ok(Delivery::Held);
+        }
+        let result = match &self.channel {
+            Channel::Desktop => data::show_desktop_notification(title, &message, true),
+            Channel::Pushover(pushover_client) => {
+                pushover_client
+                    .send_low_priority_notification(message, Some(title))
+                    .await
+            }
+            Channel::Stdout => {
+                println!("{}: {}", title, message);
+                Ok(())
+            }
+        };
+        self.settle(Severity::Normal, result)
+    }
+
+    /// Send the digest of notifications held back over the budget once the
+    /// hour is over.
+    ///
+    /// The digest is titled with the label if there is one. If it cannot be
+    /// sent, the notifications stay held for the next attempt. Returns
+    /// whether a digest was sent.
+    pub async fn flush(&self, label: Option<&String>) -> bool {
+        let budget = match &self.budget {
+            Some(budget) => budget,
+            None => return false,
+        };
+        let digest = match budget.lock().unwrap().digest(Utc::now()) {
+            Some(digest) => digest,
+            None => return false,
+        };
+        let title = label.cloned().unwrap_or_else(|| String::from("pagenine"));
+        if self.deliver(&title, digest.message.clone()).await.is_err() {
+            warn!("Could not send the digest of held back notifications");
+            return false;
+        }
+        budget.lock().unwrap().digest_sent(Utc::now(), &digest);
+        true
+    }
+
+    async fn deliver(&self, title: &String, message: String) -> Result<(), ()> {
+        match &self.channel {
+            Channel::Desktop => data::show_desktop_notification(title, &message, false),
+            Channel::Pushover(pushover_client) => {
+                pushover_client
+                    .send_notification(message, Some(title))
+                    .await
+            }
+            Channel::Stdout => {
+                println!("{}: {}", title, message.replace('\n', " - "));
+                Ok(())
+            }
+        }
+    }
+
+    /// Check the budget before delivering a notification.
+    ///
+    /// Notifications over the budget are held back for the digest.
+    fn admit(&self, severity: Severity, title: &str, message: &str) -> bool {
+        let budget = match (&self.budget, severity) {
+            (Some(budget), Severity::Normal) => budget,
+            _ => return true,
+        };
+        let line = format!("{}: {}", title, message.replace('\n', " - "));
+        let admitted = budget.lock().unwrap().admit(Utc::now(), line);
+        if !admitted {
+            info!("Notification budget exhausted, holding back \"{}\"", title);
+        }
+        admitted
+    }
+
+    fn settle(&self, severity: Severity, result: Result<(), ()>) -> Result<Delivery, ()> {
+        if let (Some(budget), Severity::Normal, Err(_)) = (&self.budget, severity, result) {
+            budget.lock().unwrap().refund();
+        }
+        result.map(|_| Delivery::Sent)
+    }
+}
+
+/// Reason why desktop notifications cannot work in this environment.
+#[derive(Debug, PartialEq)]
+pub enum Headless {
+    /// The environment variables show there is no session to notify.
+    Environment(String),
+    /// Connecting to the notification server failed.
+    Connection(String),
+}
+
+impl std::fmt::Display for Headless {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Headless::Environment(reason) => write!(f, "{}", reason),
+            Headless::Connection(reason) => {
+                write!(f, "could not reach the notification server: {}", reason)
+            }
+        }
+    }
+}
+
+/// Check the environment variables for any sign of a desktop session.
+///
+/// Notifications go through the D-Bus session bus, which is hardly ever
+/// present without a display server or an explicit bus address. Without
+/// them the bus may still be found at `$XDG_RUNTIME_DIR/bus`, in which case
+/// connecting to it decides.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn probe_environment(env: impl Fn(&str) -> Option<OsString>) -> Result<(), Headless> {
+    let is_set = |name: &str| env(name).is_some_and(|value| !value.is_empty());
+    if is_set("DISPLAY") || is_set("WAYLAND_DISPLAY") || is_set("DBUS_SESSION_BUS_ADDRESS") {
+        return Ok(());
+    }
+    let runtime_bus = env("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .is_some_and(|dir| std::path::Path::new(&dir).join("bus").exists());
+    if runtime_bus {
+        return Ok(());
+    }
+    Err(Headless::Environment(String::from(
+        "DISPLAY, WAYLAND_DISPLAY and DBUS_SESSION_BUS_ADDRESS are not set and there is no bus in XDG_RUNTIME_DIR",
+    )))
+}
+
+/// Check the environment variables for any sign of a desktop session.
+///
+/// Notifications are handled by the operating system on this platform.
+#[cfg(any(target_os = "macos", windows))]
+pub fn probe_environment(_env: impl Fn(&str) -> Option<OsString>) -> Result<(), Headless> {
+    Ok(())
+}
+
+/// Check if desktop notifications can be shown.
+///
+/// Returns a description of the notification server.
+pub async fn probe_desktop() -> Result<String, Headless> {
+    probe_environment(|name| std::env::var_os(name))?;
+    tokio::task::spawn_blocking(data::check_notification_server)
+        .await
+        .unwrap_or_else(|error| Err(error.to_string()))
+        .map_err(Headless::Connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    use test_case::test_case;
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test_case(&[], false; "nothing set")]
+    #[test_case(&[("DISPLAY", "")], false; "empty display")]
+    #[test_case(&[("TERM", "xterm")], false; "unrelated variable")]
+    #[test_case(&[("DISPLAY", ":0")], true; "x11")]
+    #[test_case(&[("WAYLAND_DISPLAY", "wayland-0")], true; "wayland")]
+    #[test_case(&[("DBUS_SESSION_BUS_ADDRESS", "unix:path=/run/user/1000/bus")], true; "session bus")]
+    fn notifier_probe_environment(variables: &[(&str, &str)], available: bool) {
+        let result = probe_environment(|name| {
+            variables
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| OsString::from(value))
+        });
+        assert_eq!(result.is_ok(), available);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test_case(true, true; "runtime bus")]
+    #[test_case(false, false; "no runtime bus")]
+    fn notifier_probe_environment_runtime_dir(bus: bool, available: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        if bus {
+            std::fs::write(dir.path().join("bus"), "").unwrap();
+        }
+        let result = probe_environment(|name| match name {
+            "XDG_RUNTIME_DIR" => Some(OsString::from(dir.path())),
+            _ => None,
+        });
+        assert_eq!(result.is_ok(), available);
+    }
+
+    #[test]
+    fn headless_display() {
+        assert_eq!(
+            Headless::Connection(String::from("no bus")).to_string(),
+            "could not reach the notification server: no bus"
+        );
+    }
+}
@@ -1,38 +1,329 @@
 use async_trait::async_trait;
-use log::error;
+use log::{error, warn};
+use serde::Deserialize;
 
-const PUSHOVER_API_URL: &str = "https://api.pushover.net/1/messages.json";
+const PUSHOVER_API_URL: &str = "https://api.pushover.net/1";
 
 #[derive(Default, Debug)]
 pub struct PushoverClient {
+    pub client: reqwest::Client,
     pub token: String,
-    pub user: String,
+    /// User or delivery group keys of everyone that gets the notifications.
+    pub users: Vec<String>,
+}
+
+impl PushoverClient {
+    /// Check that the application token and all user keys are accepted by Pushover.
+    pub async fn validate(&self) -> Result<(), String> {
+        self.validate_at(PUSHOVER_API_URL).await
+    }
+
+    async fn validate_at(&self, api_url: &str) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for user in &self.users {
+            if let Err(error) = self.validate_user(api_url, user).await {
+                errors.push(match self.users.len() {
+                    1 => error,
+                    _ => format!("{}: {}", mask(user), error),
+                });
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("; ")),
+        }
+    }
+
+    async fn validate_user(&self, api_url: &str, user: &String) -> Result<(), String> {
+        let params = [("token", &self.token), ("user", user)];
+        let response = self
+            .client
+            .post(format!("{}/users/validate.json", api_url))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let validation = response
+            .json::<ValidationResponse>()
+            .await
+            .map_err(|e| e.to_string())?;
+        match validation.status {
+            1 => Ok(()),
+            _ => Err(validation.errors.join(", ")),
+        }
+    }
+}
+
+/// Pushover API user validation response.
+#[derive(Default, Debug, Deserialize)]
+struct ValidationResponse {
+    status: i32,
+    #[serde(default)]
+    errors: Vec<String>,
 }
 
 #[async_trait]
-pub trait PushoverClientTrait {
+pub trait PushoverClientTrait: Sync {
     async fn send_notification(&self, message: String, title: Option<&String>) -> Result<(), ()>;
+
+    /// Send a notification that should not make a sound or vibrate.
+    async fn send_low_priority_notification(
+        &self,
+        message: String,
+        title: Option<&String>,
+    ) -> Result<(), ()> {
+        self.send_notification(message, title).await
+    }
 }
 
-#[async_trait]
-impl PushoverClientTrait for PushoverClient {
-    async fn send_notification(&self, message: String, title: Option<&String>) -> Result<(), ()> {
+impl PushoverClient {
+    async fn send(&self, message: String, title: Option<&String>, priority: i32) -> Result<(), ()> {
+        self.send_at(PUSHOVER_API_URL, message, title, priority)
+            .await
+    }
+
+    /// Send the message to every recipient.
+    ///
+    /// The notification counts as delivered if at least one recipient got
+    /// it. Failures for the other recipients are only logged.
+    async fn send_at(
+        &self,
+        api_url: &str,
+        message: String,
+        title: Option<&String>,
+        priority: i32,
+    ) -> Result<(), ()> {
+        let mut delivered = false;
+        for user in &self.users {
+            match self.send_to(api_url, user, &message, title, priority).await {
+                Ok(_) => delivered = true,
+                Err(e) if self.users.len() == 1 => error!("{:?}", e),
+                Err(e) => warn!("Could not notify {}: {:?}", mask(user), e),
+            }
+        }
+        match delivered {
+            true => Ok(()),
+            false => Err(()),
+        }
+    }
+
+    async fn send_to(
+        &self,
+        api_url: &str,
+        user: &String,
+        message: &String,
+        title: Option<&String>,
+        priority: i32,
+    ) -> Result<(), reqwest::Error> {
+        let priority = priority.to_string();
         let mut params = Vec::from([
             ("token", &self.token),
-            ("user", &self.user),
-            ("message", &message),
+            ("user", user),
+            ("message", message),
+            ("priority", &priority),
         ]);
         if let Some(title) = &title {
             params.push(("title", title));
         }
-        let client = reqwest::Client::new();
-        let response = match client.post(PUSHOVER_API_URL).form(&params).send().await {
-            Ok(response) => response,
-            Err(e) => {
-                error!("{:?}", e);
-                return Err(());
-            }
-        };
-        return Ok(());
+        self.client
+            .post(format!("{}/messages.json", api_url))
+            .form(&params)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Shorten a user key so that it can be told apart in logs without
+/// revealing it.
+fn mask(user: &str) -> String {
+    format!("{}...", user.chars().take(5).collect::<String>())
+}
+
+#[async_trait]
+impl PushoverClientTrait for PushoverClient {
+    async fn send_notification(&self, message: String, title: Option<&String>) -> Result<(), ()> {
+        self.send(message, title, 0).await
+    }
+
+    async fn send_low_priority_notification(
+        &self,
+        message: String,
+        title: Option<&String>,
+    ) -> Result<(), ()> {
+        self.send(message, title, -1).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_client() -> PushoverClient {
+        PushoverClient {
+            client: reqwest::Client::new(),
+            token: String::from("token"),
+            users: vec![String::from("user")],
+        }
+    }
+
+    fn make_group_client() -> PushoverClient {
+        PushoverClient {
+            users: vec![
+                String::from("alice"),
+                String::from("bobby"),
+                String::from("carol"),
+            ],
+            ..make_client()
+        }
+    }
+
+    #[tokio::test]
+    async fn pushover_validate() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/users/validate.json")
+            .match_body("token=token&user=user")
+            .with_body(r#"{"status": 1, "group": 0, "devices": ["phone"]}"#)
+            .create_async()
+            .await;
+        assert_eq!(make_client().validate_at(&server.url()).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn pushover_validate_invalid_user() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/users/validate.json")
+            .with_status(400)
+            .with_body(r#"{"user": "invalid", "errors": ["user key is invalid"], "status": 0}"#)
+            .create_async()
+            .await;
+        assert_eq!(
+            make_client().validate_at(&server.url()).await,
+            Err(String::from("user key is invalid"))
+        );
+    }
+
+    #[tokio::test]
+    async fn pushover_validate_partial_failure() {
+        let mut server = mockito::Server::new_async().await;
+        for (user, status, body) in [
+            ("alice", 200, r#"{"status": 1}"#),
+            (
+                "bobby",
+                400,
+                r#"{"user": "invalid", "errors": ["user key is invalid"], "status": 0}"#,
+            ),
+            ("carol", 200, r#"{"status": 1}"#),
+        ] {
+            server
+                .mock("POST", "/users/validate.json")
+                .match_body(format!("token=token&user={}", user).as_str())
+                .with_status(status)
+                .with_body(body)
+                .create_async()
+                .await;
+        }
+        assert_eq!(
+            make_group_client().validate_at(&server.url()).await,
+            Err(String::from("bobby...: user key is invalid"))
+        );
+    }
+
+    async fn mock_message(
+        server: &mut mockito::Server,
+        user: &str,
+        status: usize,
+    ) -> mockito::Mock {
+        server
+            .mock("POST", "/messages.json")
+            .match_body(mockito::Matcher::UrlEncoded(
+                String::from("user"),
+                String::from(user),
+            ))
+            .with_status(status)
+            .with_body(match status {
+                200 => r#"{"status": 1}"#,
+                _ => {
+                    r#"{"user": "invalid", "errors": ["user identifier is invalid"], "status": 0}"#
+                }
+            })
+            .expect(1)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn pushover_send() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/messages.json")
+            .match_body("token=token&user=user&message=%3Epage+9&priority=0&title=xyz")
+            .with_body(r#"{"status": 1}"#)
+            .create_async()
+            .await;
+        let result = make_client()
+            .send_at(
+                &server.url(),
+                String::from(">page 9"),
+                Some(&String::from("xyz")),
+                0,
+            )
+            .await;
+        assert_eq!(result, Ok(()));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn pushover_send_failure() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = mock_message(&mut server, "user", 400).await;
+        let result = make_client()
+            .send_at(&server.url(), String::from(">page 9"), None, 0)
+            .await;
+        assert_eq!(result, Err(()));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn pushover_send_partial_failure() {
+        let mut server = mockito::Server::new_async().await;
+        let mocks = [
+            mock_message(&mut server, "alice", 400).await,
+            mock_message(&mut server, "bobby", 200).await,
+            mock_message(&mut server, "carol", 500).await,
+        ];
+        let result = make_group_client()
+            .send_at(&server.url(), String::from(">page 9"), None, 0)
+            .await;
+        assert_eq!(result, Ok(()));
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn pushover_send_all_failed() {
+        let mut server = mockito::Server::new_async().await;
+        let mocks = [
+            mock_message(&mut server, "alice", 400).await,
+            mock_message(&mut server, "bobby", 400).await,
+            mock_message(&mut server, "carol", 500).await,
+        ];
+        let result = make_group_client()
+            .send_at(&server.url(), String::from(">page 9"), None, 0)
+            .await;
+        assert_eq!(result, Err(()));
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[test]
+    fn pushover_mask() {
+        assert_eq!(mask("uQiRzpo4DXghDmr9QzzfQu27cmVRsG"), "uQiRz...");
+        assert_eq!(mask("abc"), "abc...");
     }
 }
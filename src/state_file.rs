@@ -0,0 +1,186 @@
+use crate::budget::Budget;
+use crate::data;
+use chrono::prelude::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Watch state saved between runs.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateFile {
+    /// Board given on the command line or in the configuration file.
+    pub board: String,
+    pub title: String,
+    /// Board the API redirected to, if it differs from the configured one.
+    pub redirected_board: Option<String>,
+    pub thread: Option<data::Thread>,
+    pub notified: i32,
+    pub eta_notified: bool,
+    /// Notification budget of the notifier, if one is used.
+    pub budget: Option<Budget>,
+}
+
+impl StateFile {
+    /// Take the parts of the state worth keeping over a restart.
+    pub fn new(board: &str, title: &str, state: &data::State) -> StateFile {
+        StateFile {
+            board: board.to_string(),
+            title: title.to_string(),
+            redirected_board: state.board.clone(),
+            thread: state.thread.clone(),
+            notified: state.notified,
+            eta_notified: state.eta_notified,
+            budget: None,
+        }
+    }
+
+    /// Restore the saved state, leaving out the thread which has to be
+    /// reconciled with the catalog first.
+    pub fn to_state(&self) -> data::State {
+        data::State {
+            board: self.redirected_board.clone(),
+            notified: self.notified,
+            eta_notified: self.eta_notified,
+            ..data::State::new()
+        }
+    }
+
+    /// Read the state from a file, if there is one.
+    pub fn load(path: &Path) -> Result<Option<StateFile>, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Could not read {}: {}", path.display(), error)),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|error| format!("Could not parse {}: {}", path.display(), error))
+    }
+
+    /// Write the state to a file.
+    ///
+    /// The state is written to a temporary file first and moved over the
+    /// old one, so a crash in the middle never leaves a truncated file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, content)
+            .and_then(|_| std::fs::rename(&temporary, path))
+            .map_err(|error| format!("Could not write {}: {}", path.display(), error))
+    }
+
+    /// Check if the other state differs only by when the thread was last
+    /// fetched, which changes on every check but is not needed to resume.
+    pub fn same_watch(&self, other: &StateFile) -> bool {
+        let without_time = |state_file: &StateFile| StateFile {
+            thread: state_file.thread.clone().map(|thread| data::Thread {
+                time: DateTime::<Utc>::default(),
+                ..thread
+            }),
+            ..state_file.clone()
+        };
+        without_time(self) == without_time(other)
+    }
+
+    /// Check if the state was saved while watching the same thread.
+    pub fn matches(&self, board: &String, title: &String) -> bool {
+        &self.board == board && &self.title == title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_state() -> data::State {
+        data::State {
+            thread: Some(data::Thread {
+                page: 9,
+                no: 123456,
+                sub: String::from("/xyz/ - Xyz General"),
+                time: DateTime::<Utc>::from_timestamp(1700000000, 0).unwrap(),
+                position: 3,
+                page_length: 15,
+                slot: 123,
+                slot_count: 150,
+                stale: false,
+            }),
+            notified: 9,
+            board: Some(String::from("new")),
+            ..data::State::new()
+        }
+    }
+
+    #[test]
+    fn state_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let state_file = StateFile::new("vg", "/xyz/", &make_state());
+        state_file.save(&path).unwrap();
+        assert_eq!(StateFile::load(&path), Ok(Some(state_file)));
+        assert!(!dir.path().join("state.json.tmp").exists());
+    }
+
+    #[test]
+    fn state_file_to_state() {
+        let state_file = StateFile::new("vg", "/xyz/", &make_state());
+        let state = state_file.to_state();
+        assert!(state.thread.is_none());
+        assert_eq!(state.notified, 9);
+        assert_eq!(state.board, Some(String::from("new")));
+    }
+
+    #[test]
+    fn state_file_same_watch() {
+        let state = make_state();
+        let state_file = StateFile::new("vg", "/xyz/", &state);
+        let mut thread = state.thread.clone().unwrap();
+        thread.time += chrono::Duration::minutes(5);
+        let refreshed = StateFile::new(
+            "vg",
+            "/xyz/",
+            &data::State {
+                thread: Some(thread.clone()),
+                ..make_state()
+            },
+        );
+        assert!(state_file.same_watch(&refreshed));
+
+        thread.page = 10;
+        let moved = StateFile::new(
+            "vg",
+            "/xyz/",
+            &data::State {
+                thread: Some(thread),
+                ..make_state()
+            },
+        );
+        assert!(!state_file.same_watch(&moved));
+    }
+
+    #[test]
+    fn state_file_load_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(StateFile::load(&dir.path().join("state.json")), Ok(None));
+    }
+
+    #[test]
+    fn state_file_load_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(StateFile::load(&path).is_err());
+    }
+
+    #[test]
+    fn state_file_load_missing_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, r#"{"board": "vg", "title": "/xyz/"}"#).unwrap();
+        let state_file = StateFile::load(&path).unwrap().unwrap();
+        assert!(state_file.matches(&String::from("vg"), &String::from("/xyz/")));
+        assert!(!state_file.matches(&String::from("vg"), &String::from("/abc/")));
+        assert!(state_file.thread.is_none());
+    }
+}
@@ -0,0 +1,256 @@
+use crate::data;
+use chrono::prelude::{DateTime, Utc};
+use chrono::Duration;
+use std::collections::VecDeque;
+
+/// Number of samples needed before an estimate is made.
+const MIN_SAMPLES: usize = 3;
+/// Time the samples need to span before an estimate is made.
+const MIN_SPAN_MINUTES: i64 = 10;
+/// Samples older than this are forgotten so the estimate follows the pace
+/// of the current hour.
+const WINDOW_MINUTES: i64 = 60;
+/// Moving up by more than this many slots means the thread was bumped.
+const BUMP_TOLERANCE: i32 = 5;
+
+/// Position of the thread in the catalog at a point in time.
+#[derive(Clone, Debug, PartialEq)]
+struct Sample {
+    time: DateTime<Utc>,
+    slot: i32,
+}
+
+/// Recent positions of the thread used to estimate how fast it slides.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct History {
+    no: i32,
+    samples: VecDeque<Sample>,
+}
+
+impl History {
+    /// Add the current position of the thread.
+    ///
+    /// The history starts over when a different thread is recorded or the
+    /// thread got bumped, since earlier samples say nothing about the pace
+    /// from its new position.
+    pub fn record(&mut self, thread: &data::Thread) {
+        let bumped = self
+            .samples
+            .back()
+            .is_some_and(|last| last.slot - thread.slot > BUMP_TOLERANCE);
+        if thread.no != self.no || bumped {
+            self.no = thread.no;
+            self.samples.clear();
+        }
+        self.samples.push_back(Sample {
+            time: thread.time,
+            slot: thread.slot,
+        });
+        while self
+            .samples
+            .front()
+            .is_some_and(|first| thread.time - first.time > Duration::minutes(WINDOW_MINUTES))
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Check if the history has samples of a thread other than `no`.
+    pub fn is_other_thread(&self, no: i32) -> bool {
+        !self.samples.is_empty() && self.no != no
+    }
+
+    /// Estimate how many slots per minute the thread slides.
+    ///
+    /// Uses a least squares fit over the samples, so single deletions ahead
+    /// of the thread don't throw the estimate off.
+    pub fn velocity(&self) -> Option<f64> {
+        let first = self.samples.front()?;
+        let last = self.samples.back()?;
+        if self.samples.len() < MIN_SAMPLES
+            || last.time - first.time < Duration::minutes(MIN_SPAN_MINUTES)
+        {
+            return None;
+        }
+
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let minutes = (sample.time - first.time).num_seconds() as f64 / 60.0;
+                (minutes, sample.slot as f64)
+            })
+            .collect();
+        let count = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+        let covariance: f64 = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        Some(covariance / variance)
+    }
+
+    /// Estimate the time until the thread reaches the final slot.
+    ///
+    /// Threads that are not sliding at all have no estimate.
+    pub fn eta(&self, slot_count: i32) -> Option<Duration> {
+        let velocity = self.velocity()?;
+        let last = self.samples.back()?;
+        let remaining = (slot_count - last.slot).max(0) as f64;
+        if remaining == 0.0 {
+            return Some(Duration::zero());
+        }
+        if velocity <= 0.0 {
+            return None;
+        }
+        Some(Duration::seconds(
+            (remaining / velocity * 60.0).round() as i64
+        ))
+    }
+}
+
+/// What to do with the time-to-prune warning after a new estimate.
+#[derive(Debug, PartialEq)]
+pub enum EtaAction {
+    /// The estimate dropped below the threshold; warn now.
+    Notify,
+    /// The estimate recovered well above the threshold; warn again next time.
+    Rearm,
+    /// Nothing changes.
+    Keep,
+}
+
+/// Decide whether the estimate should trigger a warning.
+///
+/// Once warned, the estimate has to climb above the threshold by a margin
+/// before another warning can be sent, so an estimate hovering around the
+/// threshold doesn't warn on every check.
+pub fn evaluate(eta: Option<Duration>, threshold: Duration, notified: bool) -> EtaAction {
+    let eta = match eta {
+        Some(eta) => eta,
+        None => return EtaAction::Keep,
+    };
+    let margin = (threshold / 4).max(Duration::minutes(5));
+    if !notified && eta < threshold {
+        EtaAction::Notify
+    } else if notified && eta > threshold + margin {
+        EtaAction::Rearm
+    } else {
+        EtaAction::Keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    fn make_history(no: i32, samples: &[(i64, i32)]) -> History {
+        let start = DateTime::from_timestamp(1700000000, 0).unwrap();
+        let mut history = History::default();
+        for (minutes, slot) in samples {
+            history.record(&data::Thread {
+                no,
+                time: start + Duration::minutes(*minutes),
+                slot: *slot,
+                slot_count: 150,
+                ..Default::default()
+            });
+        }
+        history
+    }
+
+    #[test]
+    fn history_fast_slide() {
+        let history = make_history(1, &[(0, 90), (5, 100), (10, 110), (15, 120)]);
+        assert_eq!(history.velocity(), Some(2.0));
+        assert_eq!(history.eta(150), Some(Duration::minutes(15)));
+    }
+
+    #[test]
+    fn history_slow_slide() {
+        let history = make_history(1, &[(0, 30), (15, 31), (30, 32), (45, 33)]);
+        let velocity = history.velocity().unwrap();
+        assert!((velocity - 1.0 / 15.0).abs() < 1e-9);
+        assert_eq!(history.eta(150), Some(Duration::minutes(117 * 15)));
+    }
+
+    #[test]
+    fn history_noisy_slide() {
+        let history = make_history(1, &[(0, 100), (5, 110), (10, 108), (15, 120), (20, 130)]);
+        let velocity = history.velocity().unwrap();
+        assert!(velocity > 1.0 && velocity < 2.0);
+    }
+
+    #[test_case(&[]; "empty")]
+    #[test_case(&[(0, 100), (20, 120)]; "too few samples")]
+    #[test_case(&[(0, 100), (2, 104), (4, 108), (6, 112)]; "too short span")]
+    #[test_case(&[(0, 100), (10, 100), (20, 100)]; "not moving")]
+    fn history_no_estimate(samples: &[(i64, i32)]) {
+        assert_eq!(make_history(1, samples).eta(150), None);
+    }
+
+    #[test]
+    fn history_final_slot() {
+        let history = make_history(1, &[(0, 140), (5, 145), (10, 150)]);
+        assert_eq!(history.eta(150), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn history_reset_on_bump() {
+        let mut history = make_history(1, &[(0, 100), (5, 110), (10, 120)]);
+        assert!(history.velocity().is_some());
+        history.record(&data::Thread {
+            no: 1,
+            time: DateTime::from_timestamp(1700000000, 0).unwrap() + Duration::minutes(15),
+            slot: 2,
+            ..Default::default()
+        });
+        assert_eq!(history.samples.len(), 1);
+        assert_eq!(history.velocity(), None);
+    }
+
+    #[test]
+    fn history_reset_on_new_thread() {
+        let mut history = make_history(1, &[(0, 100), (5, 110), (10, 120)]);
+        history.record(&data::Thread {
+            no: 2,
+            time: DateTime::from_timestamp(1700000000, 0).unwrap() + Duration::minutes(15),
+            slot: 125,
+            ..Default::default()
+        });
+        assert_eq!(history.samples.len(), 1);
+    }
+
+    #[test]
+    fn history_is_other_thread() {
+        assert!(!History::default().is_other_thread(1));
+        let history = make_history(1, &[(0, 100)]);
+        assert!(!history.is_other_thread(1));
+        assert!(history.is_other_thread(2));
+    }
+
+    #[test]
+    fn history_window() {
+        let history = make_history(1, &[(0, 10), (30, 40), (61, 71), (70, 80)]);
+        assert_eq!(history.samples.len(), 3);
+    }
+
+    #[test_case(None, false, EtaAction::Keep; "no estimate")]
+    #[test_case(None, true, EtaAction::Keep; "no estimate after warning")]
+    #[test_case(Some(45), false, EtaAction::Keep; "above threshold")]
+    #[test_case(Some(25), false, EtaAction::Notify; "below threshold")]
+    #[test_case(Some(25), true, EtaAction::Keep; "already warned")]
+    #[test_case(Some(35), true, EtaAction::Keep; "within margin")]
+    #[test_case(Some(40), true, EtaAction::Rearm; "recovered")]
+    fn eta_evaluate(eta: Option<i64>, notified: bool, action: EtaAction) {
+        let eta = eta.map(Duration::minutes);
+        assert_eq!(evaluate(eta, Duration::minutes(30), notified), action);
+    }
+}